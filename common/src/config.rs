@@ -1,9 +1,10 @@
+use std::collections::HashMap;
+use std::io;
 use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::time::Duration;
 use serde::Serialize;
 use serde::Deserialize;
-use toml::Value::Boolean;
 
 #[derive(Debug, Clone, clap::Parser)]
 pub struct CliArgs {
@@ -17,12 +18,66 @@ pub struct Config {
     pub weather: WeatherConfig,
     pub departure: Vec<DepartureConfig>,
 
+    /// Places journeys are planned to from every configured stop
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub destination: Vec<DestinationConfig>,
+
+    /// EFA instances stops can refer to by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub providers: HashMap<String, ProviderConfig>,
+
     pub app: AppConfig,
 
+    #[serde(default)]
+    pub board: BoardConfig,
+
+    #[serde(default)]
+    pub trips: TripsConfig,
+
+    #[serde(default)]
+    pub state: StateConfig,
+
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    #[serde(default)]
+    pub admin: AdminConfig,
+
+    #[serde(default)]
+    pub http: HttpConfig,
+
     #[serde(skip)]
     pub source: Option<PathBuf>
 }
 
+impl Config {
+    /// The base URL of the EFA instance serving `departure`. An explicit `url` takes precedence over a `provider`.
+    pub fn efa_url<'a>(&'a self, departure: &'a DepartureConfig) -> crate::Result<&'a str> {
+        match (&departure.url, &departure.provider) {
+            (Some(url), _) => Ok(url),
+            (None, Some(name)) => self.providers.get(name)
+                .map(|provider| provider.url.as_str())
+                .ok_or_else(|| io::Error::other(format!("Unknown provider '{name}'"))),
+            (None, None) => Ok(EFA_API),
+        }
+    }
+
+    /// Rejects intervals of zero, which would otherwise make the background tasks ticking at them panic
+    pub fn validate(&self) -> crate::Result<()> {
+        let intervals = [
+            ("cache.departures.refreshInterval` or `lifetime", self.cache.departures.prefetch_interval()),
+            ("cache.weather.refreshInterval` or `lifetime", self.cache.weather.prefetch_interval()),
+            ("cache.sweepInterval", Duration::from_millis(self.cache.sweep_interval.into())),
+            ("state.snapshotInterval", Duration::from_millis(self.state.snapshot_interval.into())),
+        ];
+
+        match intervals.iter().find(|(_, interval)| interval.is_zero()) {
+            Some((name, _)) => Err(io::Error::other(format!("`{name}` must not be zero"))),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BindConfig {
     pub socket: SocketAddr
@@ -31,7 +86,7 @@ pub struct BindConfig {
 impl Default for BindConfig {
     fn default() -> Self {
         Self {
-            socket: SocketAddr::from(SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 1920))
+            socket: SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 1920)
         }
     }
 }
@@ -51,9 +106,74 @@ pub struct WeatherConfig {
     pub timezone: Option<String>,
 }
 
+impl DepartureConfig {
+    pub fn walking_time(&self) -> Duration {
+        Duration::from_millis(self.walking_time.unwrap_or_default().into())
+    }
+}
+
+/// The EFA instance used for stops which don't name another one
+pub const EFA_API: &str = "https://www.efa-bw.de/mobidata-bw/";
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct DepartureConfig {
     pub point: String,
+
+    /// Base URL of the EFA instance this stop belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// Name of an entry in `[providers]` this stop belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+
+    /// How long it takes to walk to the stop, so that departures which can't be reached anymore can be told apart
+    #[serde(default, rename = "walkingTime", skip_serializing_if = "Option::is_none")]
+    pub walking_time: Option<Milliseconds>,
+
+    /// Whether the board shows departures or arrivals unless a request asks otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<BoardMode>,
+
+    /// Serve departures from `<fixtures>/<point>.json`, arrivals from `<fixtures>/<point>.arrivals.json` and journeys
+    /// from `<fixtures>/<point>.trips.<destination>.json`, instead of asking the upstream. Meant for development.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixtures: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DestinationConfig {
+    /// As shown on the dashboard, and used to pick it with `?destination=`
+    pub name: String,
+
+    /// EFA stop ID, e.g. `de:08416:10103`
+    pub point: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BoardMode {
+    #[default]
+    #[serde(rename = "dep", alias = "departures")]
+    Departures,
+
+    #[serde(rename = "arr", alias = "arrivals")]
+    Arrivals,
+}
+
+impl BoardMode {
+    /// As EFA and the API's `mode` parameter spell it
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Departures => "dep",
+            Self::Arrivals => "arr",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    /// Base URL of the EFA instance, under which `XML_DM_REQUEST` and friends are found
+    pub url: String,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -64,6 +184,303 @@ pub struct AppConfig {
     pub colour_scheme: ColourScheme
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BoardConfig {
+    /// How far into the future boards may be requested with `at`
+    pub max_look_ahead: Milliseconds,
+
+    /// Requested times are rounded down to a multiple of this, so that nearby requests share a cached board
+    pub time_bucket: Milliseconds,
+
+    /// Departures which leave less than this much time to set off for the stop are marked as needing to hurry
+    pub hurry: Milliseconds,
+
+    /// IANA zone the EFA instances give their local times in
+    pub timezone: String,
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        Self {
+            max_look_ahead: 604_800_000,
+            time_bucket: 300_000,
+            hurry: 120_000,
+            timezone: "Europe/Berlin".to_owned(),
+        }
+    }
+}
+
+impl BoardConfig {
+    pub fn max_look_ahead(&self) -> Duration {
+        Duration::from_millis(self.max_look_ahead.into())
+    }
+
+    pub fn time_bucket(&self) -> Duration {
+        Duration::from_millis(self.time_bucket.into())
+    }
+
+    pub fn hurry(&self) -> Duration {
+        Duration::from_millis(self.hurry.into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TripsConfig {
+    /// How many upcoming connections are planned per stop and destination
+    pub connections: usize,
+}
+
+impl Default for TripsConfig {
+    fn default() -> Self {
+        Self {
+            connections: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StateConfig {
+    /// Where cache snapshots are kept across restarts. Nothing is persisted if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<PathBuf>,
+
+    pub snapshot_interval: Milliseconds,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            snapshot_interval: 60_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", from = "CacheSections")]
+pub struct CacheConfig {
+    pub departures: CachePolicy,
+    pub weather: CachePolicy,
+    pub city_names: CachePolicy,
+    pub trips: CachePolicy,
+
+    /// Lines serving the stops proposed by `/v1/stops/nearby`
+    pub lines: CachePolicy,
+
+    /// How often values too old to be served are removed
+    pub sweep_interval: Milliseconds,
+
+    /// Keep values in Redis instead of process memory, so that several instances can share them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redis: Option<RedisConfig>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            departures: CachePolicy {
+                lifetime: 10_000,
+                max_stale: 600_000,
+                max_entries: default_max_entries(),
+                refresh_interval: None,
+            },
+            weather: CachePolicy {
+                lifetime: 30_000,
+                max_stale: 21_600_000,
+                max_entries: default_max_entries(),
+                refresh_interval: None,
+            },
+            city_names: CachePolicy {
+                lifetime: 43_200_000,
+                max_stale: 604_800_000,
+                max_entries: default_max_entries(),
+                refresh_interval: None,
+            },
+            trips: CachePolicy {
+                lifetime: 60_000,
+                max_stale: 600_000,
+                max_entries: default_max_entries(),
+                refresh_interval: None,
+            },
+            lines: CachePolicy {
+                lifetime: 86_400_000,
+                max_stale: 604_800_000,
+                max_entries: default_max_entries(),
+                refresh_interval: None,
+            },
+            sweep_interval: 60_000,
+            redis: None,
+        }
+    }
+}
+
+/// Fields left out of a `[cache.*]` section fall back to that source's default, see [`CacheConfig::default`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachePolicy {
+    /// How long a fetched value is reused before it's fetched again
+    pub lifetime: Milliseconds,
+
+    /// How long past its lifetime a value may still be served whilst a newer one can't be obtained
+    pub max_stale: Milliseconds,
+
+    /// How many values are kept in process memory at most. Once exceeded, the ones fetched longest ago are dropped.
+    /// Doesn't apply with `[cache.redis]`, where values only expire by age.
+    pub max_entries: usize,
+
+    /// How often the value is refreshed in the background. Defaults to shortly before it expires.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_interval: Option<Milliseconds>,
+}
+
+/// `[cache]` as written in the config file, before the defaults are filled in
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct CacheSections {
+    departures: PolicySection,
+    weather: PolicySection,
+    city_names: PolicySection,
+    trips: PolicySection,
+    lines: PolicySection,
+    sweep_interval: Option<Milliseconds>,
+    redis: Option<RedisConfig>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct PolicySection {
+    lifetime: Option<Milliseconds>,
+    max_stale: Option<Milliseconds>,
+    max_entries: Option<usize>,
+    refresh_interval: Option<Milliseconds>,
+}
+
+impl PolicySection {
+    fn or(self, default: CachePolicy) -> CachePolicy {
+        CachePolicy {
+            lifetime: self.lifetime.unwrap_or(default.lifetime),
+            max_stale: self.max_stale.unwrap_or(default.max_stale),
+            max_entries: self.max_entries.unwrap_or(default.max_entries),
+            refresh_interval: self.refresh_interval.or(default.refresh_interval),
+        }
+    }
+}
+
+impl From<CacheSections> for CacheConfig {
+    fn from(sections: CacheSections) -> Self {
+        let default = Self::default();
+
+        Self {
+            departures: sections.departures.or(default.departures),
+            weather: sections.weather.or(default.weather),
+            city_names: sections.city_names.or(default.city_names),
+            trips: sections.trips.or(default.trips),
+            lines: sections.lines.or(default.lines),
+            sweep_interval: sections.sweep_interval.unwrap_or(default.sweep_interval),
+            redis: sections.redis,
+        }
+    }
+}
+
+fn default_max_entries() -> usize {
+    1024
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedisConfig {
+    pub url: String,
+
+    /// Prepended to every key, so that several deployments can share a server
+    #[serde(default = "default_redis_prefix")]
+    pub prefix: String,
+}
+
+fn default_redis_prefix() -> String {
+    "azubitafel".to_owned()
+}
+
+impl CachePolicy {
+    pub fn lifetime(&self) -> Duration {
+        Duration::from_millis(self.lifetime.into())
+    }
+
+    pub fn max_stale(&self) -> Duration {
+        Duration::from_millis(self.max_stale.into())
+    }
+
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        self.refresh_interval.map(|ms| Duration::from_millis(ms.into()))
+    }
+
+    /// How often the value is refreshed in the background. Unless configured otherwise, a little before values expire so
+    /// that handlers never find them stale.
+    pub fn prefetch_interval(&self) -> Duration {
+        self.refresh_interval()
+            .unwrap_or(self.lifetime().mul_f32(0.8))
+    }
+}
+
+/// How the API talks to EFA, open-meteo and the geocoder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HttpConfig {
+    /// How long establishing a connection may take
+    pub connect_timeout: Milliseconds,
+
+    /// How long the upstream may stay silent whilst answering
+    pub read_timeout: Milliseconds,
+
+    /// How often a failed GET is retried before giving up
+    pub retries: u32,
+
+    /// Delay before the first retry, doubling with each further one. A random part of up to the same length is added.
+    pub backoff: Milliseconds,
+
+    pub user_agent: String,
+
+    /// How many requests may be in flight to the same host at once
+    pub max_concurrency: usize,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: 5_000,
+            read_timeout: 10_000,
+            retries: 2,
+            backoff: 250,
+            user_agent: concat!("Azubitafel/", env!("CARGO_PKG_VERSION")).to_owned(),
+            max_concurrency: 4,
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout.into())
+    }
+
+    pub fn read_timeout(&self) -> Duration {
+        Duration::from_millis(self.read_timeout.into())
+    }
+
+    pub fn backoff(&self) -> Duration {
+        Duration::from_millis(self.backoff.into())
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdminConfig {
+    /// Bearer token required by the admin endpoints. They refuse every request if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
 pub type Milliseconds = u32;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -73,4 +490,22 @@ pub enum ColourScheme {
     Weather,
     Dark,
     Light
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_cache_sections_keep_their_defaults() {
+        let cache: CacheConfig = toml::from_str("[trips]\nlifetime = 120_000\n\n[cityNames]\nmaxEntries = 16\n").unwrap();
+
+        assert_eq!(cache.trips.lifetime, 120_000);
+        assert_eq!(cache.trips.max_stale, 600_000);
+
+        assert_eq!(cache.city_names.lifetime, 43_200_000);
+        assert_eq!(cache.city_names.max_entries, 16);
+
+        assert_eq!(cache.departures.lifetime, 10_000);
+        assert_eq!(cache.sweep_interval, 60_000);
+    }
+}
@@ -0,0 +1,266 @@
+//! Requests and responses of EFA's stop finder and coordinate requests, shared by the API and `stopfinder`
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchParams {
+    #[serde(rename = "outputFormat")]
+    output_format: &'static str,
+    type_sf: &'static str,
+
+    #[serde(rename = "coordOutputFormat")]
+    coord_output_format: &'static str,
+
+    #[serde(rename = "anyMaxSizeHitList")]
+    pub matches: u32,
+
+    #[serde(rename = "name_sf")]
+    pub search: String,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        Self {
+            output_format: "JSON",
+            type_sf: "any",
+            coord_output_format: "WGS84[DD.ddddd]",
+            matches: 50,
+            search: "".to_string(),
+        }
+    }
+}
+
+impl SearchParams {
+    pub fn matches(mut self, matches: u32) -> Self {
+        self.matches = matches;
+        self
+    }
+
+    pub fn search(mut self, search: impl AsRef<str>) -> Self {
+        self.search = search.as_ref().to_owned();
+        self
+    }
+}
+
+nestify::nest! {
+    #[derive(Debug, Clone, Serialize, Deserialize)]*
+    pub struct StopResponse {
+        #[serde(rename = "stopFinder")]
+        pub stops: pub struct StopFinder {
+            pub points: Vec<pub struct Point {
+                pub name: String,
+                pub object: String,
+                #[serde(rename = "ref")]
+                pub ids: pub struct IDs {
+                    pub gid: String,
+                    pub id: String,
+                    pub place: String,
+
+                    /// `longitude,latitude`
+                    #[serde(default)]
+                    pub coords: String,
+                }
+            }>
+        }
+    }
+}
+
+impl Point {
+    /// Latitude and longitude of the stop, if EFA gave any
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        coordinates(&self.ids.coords)
+    }
+}
+
+/// Parses EFA's `longitude,latitude` into latitude and longitude
+fn coordinates(coords: &str) -> Option<(f64, f64)> {
+    let (longitude, latitude) = coords.split_once(',')?;
+    Some((latitude.trim().parse().ok()?, longitude.trim().parse().ok()?))
+}
+
+/// Stops within `radius` metres of a coordinate, by `XML_COORD_REQUEST`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoordParams {
+    #[serde(rename = "outputFormat")]
+    output_format: &'static str,
+
+    #[serde(rename = "coordOutputFormat")]
+    coord_output_format: &'static str,
+
+    /// `longitude:latitude:WGS84[DD.ddddd]`
+    pub coord: String,
+
+    #[serde(rename = "inclFilter")]
+    include_filter: u8,
+
+    type_1: &'static str,
+
+    #[serde(rename = "radius_1")]
+    pub radius: u32,
+
+    #[serde(rename = "max")]
+    pub matches: u32,
+}
+
+impl Default for CoordParams {
+    fn default() -> Self {
+        Self {
+            output_format: "JSON",
+            coord_output_format: "WGS84[DD.ddddd]",
+            coord: "".to_string(),
+            include_filter: 1,
+            type_1: "STOP",
+            radius: 500,
+            matches: 10,
+        }
+    }
+}
+
+impl CoordParams {
+    pub fn around(mut self, latitude: f64, longitude: f64) -> Self {
+        self.coord = format!("{longitude:.5}:{latitude:.5}:WGS84[DD.ddddd]");
+        self
+    }
+
+    pub fn radius(mut self, radius: u32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn matches(mut self, matches: u32) -> Self {
+        self.matches = matches;
+        self
+    }
+}
+
+nestify::nest! {
+    #[derive(Debug, Clone, Serialize, Deserialize)]*
+    pub struct CoordResponse {
+        #[serde(default)]
+        pub pins: Vec<pub struct Pin {
+            pub id: String,
+
+            /// Name of the stop
+            pub desc: String,
+
+            #[serde(default)]
+            pub locality: String,
+
+            /// `longitude,latitude`
+            #[serde(default)]
+            pub coords: String,
+
+            /// In metres, as a string
+            #[serde(default)]
+            pub distance: String,
+
+            #[serde(default)]
+            pub attrs: Vec<pub struct PinAttribute {
+                pub name: String,
+                pub value: String,
+            }>,
+        }>
+    }
+}
+
+impl Pin {
+    /// The global stop ID, falling back to the instance's own ID if EFA doesn't report one
+    pub fn gid(&self) -> &str {
+        self.attrs.iter()
+            .find(|attr| attr.name == "STOP_GLOBAL_ID")
+            .map(|attr| attr.value.as_str())
+            .unwrap_or(&self.id)
+    }
+
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        coordinates(&self.coords)
+    }
+
+    /// How far the stop is from the requested coordinate in metres
+    pub fn distance(&self) -> Option<u32> {
+        self.distance.trim().parse().ok()
+    }
+
+    /// The pin as if the stop finder had found it
+    pub fn point(&self) -> Point {
+        Point {
+            name: match self.locality.as_str() {
+                "" => self.desc.clone(),
+                locality => format!("{locality}, {desc}", desc = self.desc),
+            },
+            object: self.desc.clone(),
+            ids: IDs {
+                gid: self.gid().to_owned(),
+                id: self.id.clone(),
+                place: self.locality.clone(),
+                coords: self.coords.clone(),
+            },
+        }
+    }
+}
+
+/// A departure monitor request for a stop, only to learn which lines serve it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinesParams {
+    #[serde(rename = "outputFormat")]
+    output_format: &'static str,
+
+    mode: &'static str,
+    type_dm: &'static str,
+
+    #[serde(rename = "name_dm")]
+    pub stop: String,
+
+    limit: u32,
+}
+
+impl LinesParams {
+    pub fn new(stop: impl AsRef<str>) -> Self {
+        Self {
+            output_format: "JSON",
+            mode: "direct",
+            type_dm: "any",
+            stop: stop.as_ref().to_owned(),
+            limit: 1,
+        }
+    }
+}
+
+nestify::nest! {
+    #[derive(Debug, Clone, Serialize, Deserialize)]*
+    #[serde(rename_all = "camelCase")]*
+    pub struct LinesResponse {
+        #[serde(default)]
+        pub serving_lines: Option<pub struct ServingLines {
+            #[serde(default)]
+            pub lines: Vec<pub struct ServingLine {
+                pub mode: pub struct ServingLineMode {
+                    #[serde(default)]
+                    pub number: String,
+                    #[serde(default)]
+                    pub name: String,
+                }
+            }>
+        }>
+    }
+}
+
+impl LinesResponse {
+    /// Numbers of the lines serving the stop, once each
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+
+        for line in self.serving_lines.iter().flat_map(|serving| &serving.lines) {
+            let number = match line.mode.number.as_str() {
+                "" => &line.mode.name,
+                number => number,
+            };
+
+            if !number.is_empty() && !lines.iter().any(|i| i == number) {
+                lines.push(number.to_owned());
+            }
+        }
+
+        lines
+    }
+}
@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use crate::config::{CliArgs, Config};
 
 pub mod config;
+pub mod efa;
 pub type Result<T> = core::result::Result<T, std::io::Error>;
 
 pub mod prelude {
@@ -31,6 +32,7 @@ pub async fn get_config() -> Result<Config> {
         .and_then(|config| toml::from_str(&config).map_err(io::Error::other))?;
 
     config.source = Some(PathBuf::from(&args.config));
+    config.validate()?;
 
     Ok(config)
 }
\ No newline at end of file
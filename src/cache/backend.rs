@@ -0,0 +1,42 @@
+use crate::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+use std::time::SystemTime;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CacheEntry<Value> {
+    pub entry: Value,
+    pub fetched: SystemTime,
+}
+
+impl<Value> CacheEntry<Value> {
+    pub fn age(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.fetched)
+            .unwrap_or_default()
+    }
+}
+
+/// Where a [`Cache`](super::Cache) keeps its values. Single-flight fetching and statistics are handled by the cache
+/// itself, so backends only need to store and hand back entries.
+#[async_trait]
+pub trait Backend<Key, Value>: Send + Sync {
+    async fn get(&self, key: &Key) -> Result<Option<CacheEntry<Value>>>;
+
+    /// Stores `entry`. It is never asked for again once it is older than `max_age`, so it may be dropped from then on.
+    async fn insert(&self, key: Key, entry: CacheEntry<Value>, max_age: Duration) -> Result<()>;
+
+    /// Returns whether there was an entry to remove.
+    async fn remove(&self, key: &Key) -> Result<bool>;
+
+    async fn clear(&self) -> Result<()>;
+
+    async fn keys(&self) -> Result<Vec<Key>>;
+
+    async fn entries(&self) -> Result<Vec<(Key, CacheEntry<Value>)>>;
+
+    /// Removes every entry older than `max_age`, returning how many there were.
+    async fn sweep(&self, max_age: Duration) -> Result<usize>;
+}
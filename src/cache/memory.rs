@@ -0,0 +1,120 @@
+use crate::cache::backend::Backend;
+use crate::cache::backend::CacheEntry;
+use crate::Result;
+use async_trait::async_trait;
+use common::prelude::tokio::sync::RwLock;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Keeps values in process memory. Once more than `max_entries` are stored, the ones fetched longest ago are dropped.
+pub struct MemoryBackend<Key, Value> {
+    cache: RwLock<HashMap<Key, CacheEntry<Value>>>,
+    max_entries: usize,
+}
+
+impl<Key, Value> MemoryBackend<Key, Value> {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            cache: RwLock::new(HashMap::new()),
+            max_entries,
+        }
+    }
+}
+
+#[async_trait]
+impl<Key, Value> Backend<Key, Value> for MemoryBackend<Key, Value>
+where
+    Key: Hash + Eq + Clone + Send + Sync,
+    Value: Clone + Send + Sync,
+{
+    async fn get(&self, key: &Key) -> Result<Option<CacheEntry<Value>>> {
+        Ok(self.cache.read().await.get(key).cloned())
+    }
+
+    async fn insert(&self, key: Key, entry: CacheEntry<Value>, _max_age: Duration) -> Result<()> {
+        let mut cache = self.cache.write().await;
+        cache.insert(key, entry);
+
+        while cache.len() > self.max_entries {
+            let Some(oldest) = cache.iter()
+                .min_by_key(|(_, entry)| entry.fetched)
+                .map(|(key, _)| key.clone()) else {
+                break;
+            };
+
+            cache.remove(&oldest);
+        }
+
+        Ok(())
+    }
+
+    async fn remove(&self, key: &Key) -> Result<bool> {
+        Ok(self.cache.write().await.remove(key).is_some())
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.cache.write().await.clear();
+        Ok(())
+    }
+
+    async fn keys(&self) -> Result<Vec<Key>> {
+        Ok(self.cache.read().await.keys().cloned().collect())
+    }
+
+    async fn entries(&self) -> Result<Vec<(Key, CacheEntry<Value>)>> {
+        Ok(self.cache.read().await
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect())
+    }
+
+    async fn sweep(&self, max_age: Duration) -> Result<usize> {
+        let mut cache = self.cache.write().await;
+        let before = cache.len();
+
+        cache.retain(|_, entry| entry.age() <= max_age);
+
+        Ok(before - cache.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn entry(value: u32, age: u64) -> CacheEntry<u32> {
+        CacheEntry { entry: value, fetched: SystemTime::now() - Duration::from_secs(age) }
+    }
+
+    async fn keys(backend: &MemoryBackend<&'static str, u32>) -> Vec<&'static str> {
+        let mut keys = backend.keys().await.unwrap();
+        keys.sort();
+        keys
+    }
+
+    #[actix_web::test]
+    async fn evicts_the_entries_fetched_longest_ago() {
+        let backend = MemoryBackend::new(2);
+
+        backend.insert("b", entry(2, 20), Duration::MAX).await.unwrap();
+        backend.insert("a", entry(1, 30), Duration::MAX).await.unwrap();
+        backend.insert("c", entry(3, 10), Duration::MAX).await.unwrap();
+        assert_eq!(keys(&backend).await, ["b", "c"]);
+
+        backend.insert("d", entry(4, 0), Duration::MAX).await.unwrap();
+        assert_eq!(keys(&backend).await, ["c", "d"]);
+    }
+
+    #[actix_web::test]
+    async fn sweep_removes_entries_past_max_age() {
+        let backend = MemoryBackend::new(16);
+
+        backend.insert("old", entry(1, 120), Duration::MAX).await.unwrap();
+        backend.insert("new", entry(2, 0), Duration::MAX).await.unwrap();
+
+        assert_eq!(backend.sweep(Duration::from_secs(60)).await.unwrap(), 1);
+        assert_eq!(keys(&backend).await, ["new"]);
+    }
+}
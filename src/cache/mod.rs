@@ -0,0 +1,671 @@
+mod backend;
+mod memory;
+mod redis;
+
+pub use backend::Backend;
+pub use backend::CacheEntry;
+pub use memory::MemoryBackend;
+pub use redis::RedisBackend;
+
+use crate::Error;
+use crate::Result;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
+use std::time::SystemTime;
+use common::prelude::tokio;
+use common::prelude::tokio::sync::Mutex;
+use futures::future::BoxFuture;
+use futures::future::FutureExt;
+use futures::future::Shared;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A fetch which is currently waiting on the upstream. Every caller asking for the same key awaits the same one.
+type Fetch<Value> = Shared<BoxFuture<'static, Result<Cached<Value>>>>;
+
+pub struct Cache<Key: Hash, Value> {
+    inner: Arc<Inner<Key, Value>>,
+}
+
+impl<Key: Hash, Value> Clone for Cache<Key, Value> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+struct Inner<Key, Value> {
+    backend: Box<dyn Backend<Key, Value>>,
+    inflight: Mutex<HashMap<Key, Fetch<Value>>>,
+    lifetime: Duration,
+    max_stale: Duration,
+
+    hits: AtomicU64,
+    stale_hits: AtomicU64,
+    misses: AtomicU64,
+
+    /// How often each stored value was served whilst fresh. Reset whenever the value is replaced.
+    entry_hits: std::sync::Mutex<HashMap<Key, u64>>,
+}
+
+/// A value handed out by the cache. `stale` is set if the value has outlived the cache's lifetime and is only being
+/// served because a newer one couldn't be obtained yet.
+#[derive(Debug, Clone)]
+pub struct Cached<Value> {
+    pub value: Value,
+    pub fetched: SystemTime,
+    pub stale: bool,
+}
+
+impl<Value> Deref for Cached<Value> {
+    type Target = Value;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub hits: u64,
+    pub stale_hits: u64,
+    pub misses: u64,
+    pub entries: Vec<EntryStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryStats {
+    pub key: String,
+    pub fetched: SystemTime,
+    pub expiry: SystemTime,
+    pub age: Duration,
+    pub stale: bool,
+
+    /// How often this value was served whilst still fresh
+    pub hits: u64,
+}
+
+impl<Key, Value> Inner<Key, Value> {
+    /// How old values may become before they can't even be served as stale ones anymore
+    fn max_age(&self) -> Duration {
+        self.lifetime + self.max_stale
+    }
+}
+
+impl<Key, Value> Cache<Key, Value>
+where
+    Key: Hash + Eq + Clone + Send + Sync + 'static,
+    Value: Clone + Send + Sync + 'static,
+{
+    /// Values are reused for `lifetime`. Past that they are refreshed, but continue being served for up to
+    /// `max_stale` beyond their lifetime whilst the refresh is pending or if it fails.
+    pub fn new(backend: impl Backend<Key, Value> + 'static, lifetime: Duration, max_stale: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                backend: Box::new(backend),
+                inflight: Mutex::new(HashMap::new()),
+                lifetime,
+                max_stale,
+                hits: AtomicU64::new(0),
+                stale_hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                entry_hits: std::sync::Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    pub async fn get<Fut>(&self, key: Key, or: impl FnOnce() -> Fut) -> Result<Cached<Value>>
+    where
+        Fut: Future<Output = Result<Value>> + Send + 'static,
+    {
+        let stale = match self.lookup(&key).await {
+            Some(value) if !value.stale => {
+                log::trace!("Reusing cache value: {lifetime:?}", lifetime = self.inner.lifetime);
+                self.inner.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(value);
+            }
+            stale => stale,
+        };
+
+        // The backend is only read outside the lock, so that a slow one never holds up fetches of other keys
+        let fetch = {
+            let mut inflight = self.inner.inflight.lock().await;
+            self.join_or_fetch(&mut inflight, key, or, true)
+        };
+
+        if let Some(stale) = stale {
+            log::trace!("Serving stale value whilst revalidating");
+            self.inner.stale_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(stale);
+        }
+
+        self.inner.misses.fetch_add(1, Ordering::Relaxed);
+        fetch.await
+    }
+
+    /// Fetches a new value for `key` regardless of whether the stored one is still fresh.
+    pub async fn refresh<Fut>(&self, key: Key, or: impl FnOnce() -> Fut) -> Result<Cached<Value>>
+    where
+        Fut: Future<Output = Result<Value>> + Send + 'static,
+    {
+        let fetch = {
+            let mut inflight = self.inner.inflight.lock().await;
+            self.join_or_fetch(&mut inflight, key, or, false)
+        };
+
+        fetch.await
+    }
+
+    /// Removes every value which has become too old to be served, returning how many there were.
+    pub async fn sweep(&self) -> Result<usize> {
+        let swept = self.inner.backend.sweep(self.inner.max_age()).await?;
+
+        let keys = self.inner.backend.keys().await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        self.entry_hits().retain(|key, _| keys.contains(key));
+
+        Ok(swept)
+    }
+
+    pub async fn clear(&self) -> Result<()> {
+        self.inner.backend.clear().await?;
+        self.entry_hits().clear();
+
+        Ok(())
+    }
+
+    /// Returns the stored value for `key` unless it is too old to be served at all.
+    async fn lookup(&self, key: &Key) -> Option<Cached<Value>> {
+        let entry = match self.inner.backend.get(key).await {
+            Ok(entry) => entry?,
+            Err(err) => {
+                log::warn!("Failed to read from cache: {err}");
+                return None;
+            }
+        };
+
+        let age = entry.age();
+
+        if age > self.inner.max_age() {
+            return None;
+        }
+
+        let stale = age > self.inner.lifetime;
+        if !stale {
+            *self.entry_hits().entry(key.clone()).or_default() += 1;
+        }
+
+        Some(Cached {
+            value: entry.entry,
+            fetched: entry.fetched,
+            stale,
+        })
+    }
+
+    fn entry_hits(&self) -> std::sync::MutexGuard<'_, HashMap<Key, u64>> {
+        self.inner.entry_hits.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Joins the fetch in flight for `key`, or starts a new one. With `recheck`, a new fetch first looks whether a
+    /// fresh value has been stored since the caller's lookup and only asks the upstream if there's none.
+    fn join_or_fetch<Fut>(&self, inflight: &mut HashMap<Key, Fetch<Value>>, key: Key, or: impl FnOnce() -> Fut, recheck: bool) -> Fetch<Value>
+    where
+        Fut: Future<Output = Result<Value>> + Send + 'static,
+    {
+        match inflight.get(&key) {
+            Some(fetch) => {
+                log::trace!("Awaiting in-flight fetch");
+                fetch.clone()
+            }
+            None => {
+                log::trace!("Fetching new value");
+                let fetch = self.fetch(key.clone(), or(), recheck);
+                inflight.insert(key, fetch.clone());
+
+                // Driven on its own, so that it completes even if every caller stops waiting for it
+                tokio::spawn(fetch.clone());
+                fetch
+            }
+        }
+    }
+
+    /// Wraps `fut` so that it stores its own result once it resolves. This way the value is kept even if every
+    /// caller waiting on it has gone away in the meantime.
+    fn fetch(&self, key: Key, fut: impl Future<Output = Result<Value>> + Send + 'static, recheck: bool) -> Fetch<Value> {
+        let inner: Weak<Inner<Key, Value>> = Arc::downgrade(&self.inner);
+
+        async move {
+            // Another fetch may have completed between the caller's lookup and this one being started. Completed
+            // fetches store their value before leaving `inflight`, so checking here means we never ask the upstream
+            // for a value which is already fresh.
+            let stored = match inner.upgrade() {
+                Some(inner) if recheck => Cache { inner }.lookup(&key).await.filter(|value| !value.stale),
+                _ => None,
+            };
+
+            if let Some(stored) = stored {
+                if let Some(inner) = inner.upgrade() {
+                    inner.inflight.lock().await.remove(&key);
+                }
+
+                return Ok(stored);
+            }
+
+            let value = fut.await.map(|value| Cached {
+                value,
+                fetched: SystemTime::now(),
+                stale: false,
+            });
+
+            if let Some(inner) = inner.upgrade() {
+                match &value {
+                    Ok(value) => {
+                        let entry = CacheEntry {
+                            entry: value.value.clone(),
+                            fetched: value.fetched,
+                        };
+
+                        if let Err(err) = inner.backend.insert(key.clone(), entry, inner.max_age()).await {
+                            log::warn!("Failed to write to cache: {err}");
+                        }
+
+                        inner.entry_hits.lock().unwrap_or_else(|err| err.into_inner()).remove(&key);
+                    }
+                    Err(err) => log::warn!("Failed to refresh cache value: {err}"),
+                }
+
+                inner.inflight.lock().await.remove(&key);
+            }
+
+            value
+        }
+        .boxed()
+        .shared()
+    }
+}
+
+impl<Key, Value> Cache<Key, Value>
+where
+    Key: Hash + Eq + Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+    Value: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    pub async fn stats(&self) -> Result<CacheStats> {
+        let stored = self.inner.backend.entries().await?;
+        let hits = self.entry_hits();
+
+        let mut entries = stored.iter()
+            .map(|(key, entry)| EntryStats {
+                key: display_key(key),
+                fetched: entry.fetched,
+                expiry: entry.fetched + self.inner.lifetime,
+                age: entry.age(),
+                stale: entry.age() > self.inner.lifetime,
+                hits: hits.get(key).copied().unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(CacheStats {
+            hits: self.inner.hits.load(Ordering::Relaxed),
+            stale_hits: self.inner.stale_hits.load(Ordering::Relaxed),
+            misses: self.inner.misses.load(Ordering::Relaxed),
+            entries,
+        })
+    }
+
+    /// Removes the value whose key is listed as `key` in [`Cache::stats`]. Returns whether there was one.
+    pub async fn invalidate(&self, key: &str) -> Result<bool> {
+        let Some(key) = self.inner.backend.keys().await?
+            .into_iter()
+            .find(|i| display_key(i) == key) else {
+            return Ok(false);
+        };
+
+        self.entry_hits().remove(&key);
+        self.inner.backend.remove(&key).await
+    }
+
+    /// Writes every stored value to `path` along with the time it was fetched.
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let snapshot = serde_json::to_vec(&self.inner.backend.entries().await?).map_err(Error::internal)?;
+
+        // Write next to the snapshot first so that a crash halfway through never leaves a truncated one behind
+        let partial = path.as_ref().with_extension("partial");
+        tokio::fs::write(&partial, snapshot).await?;
+        Ok(tokio::fs::rename(&partial, path).await?)
+    }
+
+    /// Restores values written by [`Cache::save`]. Their original fetch time is kept, so they expire as if the
+    /// process had never stopped. Values which are already present and newer than the snapshot are left alone.
+    pub async fn load(&self, path: impl AsRef<Path>) -> Result<()> {
+        let snapshot = match tokio::fs::read(path).await {
+            Ok(snapshot) => snapshot,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let entries: Vec<(Key, CacheEntry<Value>)> = serde_json::from_slice(&snapshot).map_err(Error::internal)?;
+
+        for (key, entry) in entries {
+            let newer = self.inner.backend.get(&key).await?
+                .is_some_and(|existing| existing.fetched >= entry.fetched);
+
+            if !newer {
+                self.inner.backend.insert(key, entry, self.inner.max_age()).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Keys are listed by their JSON representation, except for plain strings which are shown as they are.
+fn display_key(key: &impl Serialize) -> String {
+    match serde_json::to_value(key) {
+        Ok(serde_json::Value::String(key)) => key,
+        Ok(key) => key.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::prelude::tokio::sync::oneshot;
+    use std::sync::atomic::AtomicUsize;
+
+    fn cache(lifetime: u64, max_stale: u64) -> Cache<String, u32> {
+        Cache::new(MemoryBackend::new(16), Duration::from_millis(lifetime), Duration::from_millis(max_stale))
+    }
+
+    /// Counts how often the cache asked for a value
+    #[derive(Clone, Default)]
+    struct Upstream(Arc<AtomicUsize>);
+
+    impl Upstream {
+        fn fetch(&self, value: Result<u32>) -> impl Future<Output = Result<u32>> + Send + use<> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            async move { value }
+        }
+
+        fn calls(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    #[actix_web::test]
+    async fn concurrent_gets_fetch_once() {
+        let cache = cache(60_000, 0);
+        let upstream = Upstream::default();
+
+        let gets = (0..10).map(|_| {
+            let upstream = upstream.clone();
+
+            cache.get("a".to_owned(), move || {
+                let fetch = upstream.fetch(Ok(42));
+                async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    fetch.await
+                }
+            })
+        });
+
+        for value in futures::future::join_all(gets).await {
+            assert_eq!(value.expect("Fetch failed").value, 42);
+        }
+
+        assert_eq!(upstream.calls(), 1);
+    }
+
+    #[actix_web::test]
+    async fn slow_fetch_does_not_block_other_keys() {
+        let cache = cache(60_000, 0);
+        let (_never, pending) = oneshot::channel::<u32>();
+
+        let slow = cache.clone();
+        let slow = tokio::spawn(async move {
+            slow.get("a".to_owned(), || async move { pending.await.map_err(Error::internal) }).await
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let b = tokio::time::timeout(Duration::from_secs(1), cache.get("b".to_owned(), || async { Ok(2) })).await
+            .expect("Fetching b waited on a")
+            .expect("Fetch failed");
+
+        assert_eq!(b.value, 2);
+        slow.abort();
+    }
+
+    /// Hangs on every read of `a` after the first, like a backend whose server has stopped responding
+    struct Unresponsive {
+        backend: MemoryBackend<String, u32>,
+        reads: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Backend<String, u32> for Unresponsive {
+        async fn get(&self, key: &String) -> Result<Option<CacheEntry<u32>>> {
+            if key == "a" && self.reads.fetch_add(1, Ordering::SeqCst) > 0 {
+                futures::future::pending::<()>().await;
+            }
+
+            self.backend.get(key).await
+        }
+
+        async fn insert(&self, key: String, entry: CacheEntry<u32>, max_age: Duration) -> Result<()> {
+            self.backend.insert(key, entry, max_age).await
+        }
+
+        async fn remove(&self, key: &String) -> Result<bool> {
+            self.backend.remove(key).await
+        }
+
+        async fn clear(&self) -> Result<()> {
+            self.backend.clear().await
+        }
+
+        async fn keys(&self) -> Result<Vec<String>> {
+            self.backend.keys().await
+        }
+
+        async fn entries(&self) -> Result<Vec<(String, CacheEntry<u32>)>> {
+            self.backend.entries().await
+        }
+
+        async fn sweep(&self, max_age: Duration) -> Result<usize> {
+            self.backend.sweep(max_age).await
+        }
+    }
+
+    #[actix_web::test]
+    async fn slow_backend_does_not_block_other_keys() {
+        let backend = Unresponsive { backend: MemoryBackend::new(16), reads: AtomicUsize::new(0) };
+        let cache = Cache::new(backend, Duration::from_secs(60), Duration::ZERO);
+
+        let slow = cache.clone();
+        let slow = tokio::spawn(async move { slow.get("a".to_owned(), || async { Ok(1) }).await });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let b = tokio::time::timeout(Duration::from_secs(1), cache.get("b".to_owned(), || async { Ok(2) })).await
+            .expect("Fetching b waited on the backend reading a")
+            .expect("Fetch failed");
+
+        assert_eq!(b.value, 2);
+        slow.abort();
+    }
+
+    #[actix_web::test]
+    async fn dropped_caller_still_stores_value() {
+        let cache = cache(60_000, 0);
+        let (send, receive) = oneshot::channel::<u32>();
+
+        let caller = cache.clone();
+        let caller = tokio::spawn(async move {
+            caller.get("a".to_owned(), || async move { receive.await.map_err(Error::internal) }).await
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        caller.abort();
+        let _ = caller.await;
+
+        send.send(7).expect("Fetch was dropped along with its caller");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let stored = cache.lookup(&"a".to_owned()).await.expect("Value wasn't stored");
+        assert_eq!(stored.value, 7);
+        assert!(cache.inner.inflight.lock().await.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn stale_value_is_served_whilst_revalidating() {
+        let cache = cache(30, 60_000);
+        let upstream = Upstream::default();
+
+        cache.get("a".to_owned(), || upstream.fetch(Ok(1))).await.expect("Fetch failed");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let stale = cache.get("a".to_owned(), || upstream.fetch(Ok(2))).await.expect("Stale value wasn't served");
+        assert_eq!(stale.value, 1);
+        assert!(stale.stale);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let fresh = cache.get("a".to_owned(), || upstream.fetch(Ok(3))).await.expect("Fetch failed");
+        assert_eq!(fresh.value, 2);
+        assert!(!fresh.stale);
+        assert_eq!(upstream.calls(), 2);
+    }
+
+    #[actix_web::test]
+    async fn failed_refresh_serves_stale_value() {
+        let cache = cache(30, 60_000);
+        let upstream = Upstream::default();
+
+        cache.get("a".to_owned(), || upstream.fetch(Ok(1))).await.expect("Fetch failed");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        for _ in 0..2 {
+            let stale = cache.get("a".to_owned(), || upstream.fetch(Err(Error::UpstreamTimeout("test".to_owned())))).await
+                .expect("Stale value wasn't served");
+
+            assert_eq!(stale.value, 1);
+            assert!(stale.stale);
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Each failed refresh made way for the next one
+        assert_eq!(upstream.calls(), 3);
+    }
+
+    #[actix_web::test]
+    async fn too_stale_value_is_not_served() {
+        let cache = cache(20, 20);
+        let upstream = Upstream::default();
+
+        cache.get("a".to_owned(), || upstream.fetch(Ok(1))).await.expect("Fetch failed");
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        let err = cache.get("a".to_owned(), || upstream.fetch(Err(Error::UpstreamTimeout("test".to_owned())))).await;
+        assert!(matches!(err, Err(Error::UpstreamTimeout(_))));
+    }
+
+    #[actix_web::test]
+    async fn failed_refresh_leaves_inflight() {
+        let cache = cache(60_000, 0);
+        let upstream = Upstream::default();
+
+        let err = cache.get("a".to_owned(), || upstream.fetch(Err(Error::UpstreamTimeout("test".to_owned())))).await;
+        assert!(err.is_err());
+        assert!(cache.inner.inflight.lock().await.is_empty());
+
+        let value = cache.get("a".to_owned(), || upstream.fetch(Ok(1))).await.expect("Failed fetch was reused");
+        assert_eq!(value.value, 1);
+        assert_eq!(upstream.calls(), 2);
+    }
+
+    #[actix_web::test]
+    async fn stats_and_invalidate_by_display_key() {
+        let cache: Cache<(String, u32), u32> = Cache::new(MemoryBackend::new(16), Duration::from_secs(60), Duration::ZERO);
+        let upstream = Upstream::default();
+
+        cache.get(("a".to_owned(), 1), || upstream.fetch(Ok(1))).await.expect("Fetch failed");
+        cache.get(("a".to_owned(), 1), || upstream.fetch(Ok(1))).await.expect("Fetch failed");
+        cache.get(("b".to_owned(), 2), || upstream.fetch(Ok(2))).await.expect("Fetch failed");
+
+        let stats = cache.stats().await.expect("No stats");
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+
+        let keys = stats.entries.iter().map(|entry| (entry.key.as_str(), entry.hits)).collect::<Vec<_>>();
+        assert_eq!(keys, [(r#"["a",1]"#, 1), (r#"["b",2]"#, 0)]);
+
+        assert!(cache.invalidate(r#"["a",1]"#).await.expect("Invalidating failed"));
+        assert!(!cache.invalidate(r#"["a",1]"#).await.expect("Invalidating failed"));
+        assert!(!cache.invalidate("a").await.expect("Invalidating failed"));
+
+        let keys = cache.stats().await.expect("No stats").entries.into_iter().map(|entry| entry.key).collect::<Vec<_>>();
+        assert_eq!(keys, [r#"["b",2]"#]);
+    }
+
+    #[test]
+    fn plain_string_keys_are_displayed_as_they_are() {
+        assert_eq!(display_key(&"de:08416:10104"), "de:08416:10104");
+        assert_eq!(display_key(&Some(3)), "3");
+    }
+
+    #[actix_web::test]
+    async fn snapshot_round_trip_keeps_fetch_times() {
+        let dir = std::env::temp_dir().join(format!("azubitafel-snapshot-{id:x}", id = fastrand::u64(..)));
+        tokio::fs::create_dir_all(&dir).await.expect("Failed to create snapshot directory");
+        let path = dir.join("cache.json");
+
+        let at = |secs| SystemTime::now() - Duration::from_secs(secs);
+        let (a, b, c) = (at(5), at(10), at(20));
+
+        let saved = cache(60_000, 0);
+        saved.inner.backend.insert("a".to_owned(), CacheEntry { entry: 1, fetched: a }, Duration::MAX).await.unwrap();
+        saved.inner.backend.insert("b".to_owned(), CacheEntry { entry: 2, fetched: b }, Duration::MAX).await.unwrap();
+        saved.inner.backend.insert("c".to_owned(), CacheEntry { entry: 3, fetched: b }, Duration::MAX).await.unwrap();
+        saved.save(&path).await.expect("Saving failed");
+
+        // Fetched after the snapshot was taken, and before
+        let loaded = cache(60_000, 0);
+        loaded.inner.backend.insert("b".to_owned(), CacheEntry { entry: 20, fetched: a }, Duration::MAX).await.unwrap();
+        loaded.inner.backend.insert("c".to_owned(), CacheEntry { entry: 30, fetched: c }, Duration::MAX).await.unwrap();
+        loaded.load(&path).await.expect("Loading failed");
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+
+        let entry = loaded.inner.backend.get(&"a".to_owned()).await.unwrap().expect("Value wasn't restored");
+        assert_eq!((entry.entry, entry.fetched), (1, a));
+
+        let entry = loaded.inner.backend.get(&"b".to_owned()).await.unwrap().expect("Value was lost");
+        assert_eq!((entry.entry, entry.fetched), (20, a), "Newer value was overwritten by the snapshot");
+
+        let entry = loaded.inner.backend.get(&"c".to_owned()).await.unwrap().expect("Value was lost");
+        assert_eq!((entry.entry, entry.fetched), (3, b));
+    }
+
+    #[actix_web::test]
+    async fn missing_snapshot_is_ignored() {
+        let path = std::env::temp_dir().join(format!("azubitafel-missing-{id:x}.json", id = fastrand::u64(..)));
+        cache(60_000, 0).load(&path).await.expect("Missing snapshot wasn't ignored");
+    }
+}
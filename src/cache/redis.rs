@@ -0,0 +1,216 @@
+use crate::cache::backend::Backend;
+use crate::cache::backend::CacheEntry;
+use crate::Error;
+use crate::Result;
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// Keeps values in Redis so that several replicas of the API can share them. Keys and values are stored as JSON
+/// underneath `prefix`, and expire on their own once they're too old to be served.
+pub struct RedisBackend<Key, Value> {
+    connection: ConnectionManager,
+    prefix: String,
+    types: PhantomData<fn() -> (Key, Value)>,
+}
+
+impl<Key, Value> RedisBackend<Key, Value> {
+    pub async fn connect(url: &str, prefix: impl Into<String>) -> Result<Self> {
+        let connection = redis::Client::open(url)
+            .map_err(Error::internal)?
+            .get_connection_manager()
+            .await
+            .map_err(Error::internal)?;
+
+        Ok(Self {
+            connection,
+            prefix: prefix.into(),
+            types: PhantomData,
+        })
+    }
+
+    fn key(&self, key: &impl Serialize) -> Result<String> {
+        let key = serde_json::to_string(key).map_err(Error::internal)?;
+        Ok(format!("{prefix}:{key}", prefix = self.prefix))
+    }
+
+    async fn scan(&self) -> Result<Vec<String>> {
+        let mut connection = self.connection.clone();
+        let mut iter = connection.scan_match::<_, String>(format!("{prefix}:*", prefix = self.prefix)).await
+            .map_err(Error::internal)?;
+
+        let mut keys = Vec::new();
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+
+        Ok(keys)
+    }
+}
+
+#[async_trait]
+impl<Key, Value> Backend<Key, Value> for RedisBackend<Key, Value>
+where
+    Key: Serialize + DeserializeOwned + Send + Sync,
+    Value: Serialize + DeserializeOwned + Send + Sync,
+{
+    async fn get(&self, key: &Key) -> Result<Option<CacheEntry<Value>>> {
+        let entry: Option<Vec<u8>> = self.connection.clone()
+            .get(self.key(key)?).await
+            .map_err(Error::internal)?;
+
+        entry.map(|entry| serde_json::from_slice(&entry).map_err(Error::internal))
+            .transpose()
+    }
+
+    async fn insert(&self, key: Key, entry: CacheEntry<Value>, max_age: Duration) -> Result<()> {
+        let expiry = max_age.saturating_sub(entry.age()).as_millis() as u64;
+
+        if expiry == 0 {
+            return Ok(());
+        }
+
+        let value = serde_json::to_vec(&entry).map_err(Error::internal)?;
+
+        self.connection.clone()
+            .pset_ex::<_, _, ()>(self.key(&key)?, value, expiry).await
+            .map_err(Error::internal)
+    }
+
+    async fn remove(&self, key: &Key) -> Result<bool> {
+        let removed: usize = self.connection.clone()
+            .del(self.key(key)?).await
+            .map_err(Error::internal)?;
+
+        Ok(removed > 0)
+    }
+
+    async fn clear(&self) -> Result<()> {
+        let keys = self.scan().await?;
+
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        self.connection.clone()
+            .del::<_, ()>(keys).await
+            .map_err(Error::internal)
+    }
+
+    async fn keys(&self) -> Result<Vec<Key>> {
+        let prefix = format!("{prefix}:", prefix = self.prefix);
+
+        self.scan().await?
+            .iter()
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .map(|key| serde_json::from_str(key).map_err(Error::internal))
+            .collect()
+    }
+
+    async fn entries(&self) -> Result<Vec<(Key, CacheEntry<Value>)>> {
+        let keys = self.keys().await?;
+
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let names = keys.iter()
+            .map(|key| self.key(key))
+            .collect::<Result<Vec<_>>>()?;
+
+        let entries: Vec<Option<Vec<u8>>> = self.connection.clone()
+            .mget(names).await
+            .map_err(Error::internal)?;
+
+        // Entries can expire between listing and fetching them, in which case they're simply left out
+        keys.into_iter()
+            .zip(entries)
+            .filter_map(|(key, entry)| entry.map(|entry| (key, entry)))
+            .map(|(key, entry)| Ok((key, serde_json::from_slice(&entry).map_err(Error::internal)?)))
+            .collect()
+    }
+
+    async fn sweep(&self, _max_age: Duration) -> Result<usize> {
+        // Redis expires entries by itself
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    /// Runs against the server in `REDIS_URL`. These tests are ignored by default, so run them with e.g.
+    /// `REDIS_URL=redis://127.0.0.1 cargo test -- --ignored`.
+    async fn backend(prefix: &str) -> RedisBackend<String, Vec<u32>> {
+        let url = std::env::var("REDIS_URL").expect("REDIS_URL isn't set");
+        let backend = RedisBackend::connect(&url, format!("azubitafel-test:{prefix}")).await
+            .expect("Failed to connect to Redis");
+
+        backend.clear().await.expect("Failed to clear test keys");
+        backend
+    }
+
+    fn entry(value: Vec<u32>) -> CacheEntry<Vec<u32>> {
+        CacheEntry {
+            entry: value,
+            fetched: SystemTime::now(),
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a Redis server in REDIS_URL"]
+    async fn round_trip() -> Result<()> {
+        let backend = backend("round-trip").await;
+
+        backend.insert("a".to_owned(), entry(vec![1, 2, 3]), Duration::from_secs(60)).await?;
+        backend.insert("b".to_owned(), entry(vec![4]), Duration::from_secs(60)).await?;
+
+        let a = backend.get(&"a".to_owned()).await?.expect("Missing entry");
+        assert_eq!(a.entry, vec![1, 2, 3]);
+        assert!(backend.get(&"c".to_owned()).await?.is_none());
+
+        let mut keys = backend.keys().await?;
+        keys.sort();
+        assert_eq!(keys, ["a", "b"]);
+        assert_eq!(backend.entries().await?.len(), 2);
+
+        assert!(backend.remove(&"a".to_owned()).await?);
+        assert!(!backend.remove(&"a".to_owned()).await?);
+
+        backend.clear().await?;
+        assert!(backend.keys().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a Redis server in REDIS_URL"]
+    async fn expires() -> Result<()> {
+        let backend = backend("expires").await;
+
+        backend.insert("a".to_owned(), entry(vec![1]), Duration::from_millis(100)).await?;
+        assert!(backend.get(&"a".to_owned()).await?.is_some());
+
+        actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+        assert!(backend.get(&"a".to_owned()).await?.is_none());
+
+        Ok(())
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a Redis server in REDIS_URL"]
+    async fn shared_between_connections() -> Result<()> {
+        let (first, second) = (backend("shared").await, backend("shared").await);
+
+        first.insert("a".to_owned(), entry(vec![7]), Duration::from_secs(60)).await?;
+        assert_eq!(second.get(&"a".to_owned()).await?.map(|entry| entry.entry), Some(vec![7]));
+
+        first.clear().await
+    }
+}
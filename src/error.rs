@@ -0,0 +1,224 @@
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use actix_web::ResponseError;
+use serde::ser::SerializeStruct;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use std::fmt;
+use std::io;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Everything that can go wrong whilst serving a request. Each kind maps onto the status code the client sees, with the
+/// details in an `application/problem+json` body.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// The upstream couldn't be reached, or answered with an error status
+    UpstreamUnreachable(String),
+
+    /// The upstream took too long to answer
+    UpstreamTimeout(String),
+
+    /// The upstream answered with something that doesn't fit its schema
+    SchemaMismatch(String),
+
+    /// The request's parameters don't make sense
+    BadRequest(String),
+
+    /// The config is invalid, or asks for something a provider can't do
+    Config(String),
+
+    /// The requested stop, destination, provider or cache isn't configured
+    NotFound(String),
+
+    /// The admin token is missing or wrong
+    Unauthorized,
+
+    /// Anything else, e.g. reading a snapshot or talking to Redis
+    Internal(String),
+}
+
+impl Error {
+    /// For errors which are neither the upstream's nor the client's fault
+    pub fn internal(err: impl fmt::Display) -> Self {
+        Self::Internal(err.to_string())
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::UpstreamUnreachable(_) => "Upstream unreachable",
+            Self::UpstreamTimeout(_) => "Upstream timed out",
+            Self::SchemaMismatch(_) => "Unexpected upstream response",
+            Self::BadRequest(_) => "Bad request",
+            Self::Config(_) => "Configuration error",
+            Self::NotFound(_) => "Not found",
+            Self::Unauthorized => "Unauthorized",
+            Self::Internal(_) => "Internal error",
+        }
+    }
+
+    fn detail(&self) -> Option<&str> {
+        match self {
+            Self::UpstreamUnreachable(detail)
+            | Self::UpstreamTimeout(detail)
+            | Self::SchemaMismatch(detail)
+            | Self::BadRequest(detail)
+            | Self::Config(detail)
+            | Self::NotFound(detail)
+            | Self::Internal(detail) => Some(detail),
+            Self::Unauthorized => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.detail() {
+            Some(detail) => write!(f, "{title}: {detail}", title = self.title()),
+            None => f.write_str(self.title()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::UpstreamUnreachable(_) | Self::SchemaMismatch(_) => StatusCode::BAD_GATEWAY,
+            Self::UpstreamTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Config(_) | Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+
+        if status.is_server_error() {
+            log::warn!("{self}");
+        }
+
+        HttpResponse::build(status)
+            .content_type("application/problem+json")
+            .json(self)
+    }
+}
+
+/// As problem details, which is also how parts of a partial response report their failure
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let mut problem = serializer.serialize_struct("Problem", 4)?;
+        problem.serialize_field("type", "about:blank")?;
+        problem.serialize_field("title", self.title())?;
+        problem.serialize_field("status", &self.status_code().as_u16())?;
+
+        match self.detail() {
+            Some(detail) => problem.serialize_field("detail", detail)?,
+            None => problem.skip_field("detail")?,
+        }
+
+        problem.end()
+    }
+}
+
+/// Reads problem details back by their title, so that errors can be cached along with the values they affected
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Problem {
+            title: String,
+            #[serde(default)]
+            detail: String,
+        }
+
+        let Problem { title, detail } = Problem::deserialize(deserializer)?;
+
+        let kinds = [
+            Self::UpstreamUnreachable(detail.clone()),
+            Self::UpstreamTimeout(detail.clone()),
+            Self::SchemaMismatch(detail.clone()),
+            Self::BadRequest(detail.clone()),
+            Self::Config(detail.clone()),
+            Self::NotFound(detail.clone()),
+            Self::Unauthorized,
+        ];
+
+        Ok(kinds.into_iter()
+            .find(|kind| kind.title() == title)
+            .unwrap_or(Self::Internal(detail)))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::TimedOut => Self::UpstreamTimeout(err.to_string()),
+            io::ErrorKind::InvalidData => Self::SchemaMismatch(err.to_string()),
+            _ => Self::Internal(err.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::UpstreamTimeout(err.to_string())
+        } else if err.is_decode() {
+            Self::SchemaMismatch(err.to_string())
+        } else if err.is_builder() {
+            Self::Internal(err.to_string())
+        } else {
+            Self::UpstreamUnreachable(err.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::SchemaMismatch(err.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(err: std::num::ParseIntError) -> Self {
+        Self::SchemaMismatch(err.to_string())
+    }
+}
+
+impl From<chrono::ParseError> for Error {
+    fn from(err: chrono::ParseError) -> Self {
+        Self::SchemaMismatch(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_as_problem() {
+        let errors = [
+            Error::UpstreamUnreachable("connection refused".to_owned()),
+            Error::UpstreamTimeout("after 10s".to_owned()),
+            Error::SchemaMismatch("missing field".to_owned()),
+            Error::BadRequest("`limit` is too large".to_owned()),
+            Error::Config("unknown provider".to_owned()),
+            Error::NotFound("no such stop".to_owned()),
+            Error::Unauthorized,
+            Error::Internal("disk full".to_owned()),
+        ];
+
+        for error in errors {
+            let problem = serde_json::to_value(&error).unwrap();
+            assert_eq!(problem["status"], error.status_code().as_u16());
+
+            let read: Error = serde_json::from_value(problem).unwrap();
+            assert_eq!(read.to_string(), error.to_string());
+        }
+    }
+}
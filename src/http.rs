@@ -0,0 +1,123 @@
+use crate::Error;
+use crate::Result;
+use common::config::HttpConfig;
+use common::prelude::tokio;
+use common::prelude::tokio::sync::Semaphore;
+use reqwest::header;
+use reqwest::StatusCode;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The client every request to an upstream goes through, configured by the `[http]` config section. Clones share
+/// their connections and concurrency limits.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    config: HttpConfig,
+
+    /// One semaphore per host, so that a slow upstream can't hold up requests to the others
+    limits: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl HttpClient {
+    pub fn new(config: &HttpConfig) -> Result<Self> {
+        let client = reqwest::ClientBuilder::new()
+            .user_agent(&config.user_agent)
+            .connect_timeout(config.connect_timeout())
+            .read_timeout(config.read_timeout())
+            .build()
+            .map_err(|err| Error::Config(format!("Invalid HTTP client settings: {err}")))?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            limits: Arc::default(),
+        })
+    }
+
+    /// GETs `url` and parses its JSON body. Failures which might go away on their own, like timeouts or 5xx statuses,
+    /// are retried with exponential backoff.
+    pub async fn get<T: DeserializeOwned>(&self, url: Url) -> Result<T> {
+        let limit = self.limit(&url);
+        let mut attempt = 0;
+
+        loop {
+            let result = {
+                let _permit = limit.acquire().await.map_err(Error::internal)?;
+                self.fetch(url.clone()).await
+            };
+
+            match result {
+                Err(err) if attempt < self.config.retries && retryable(&err) => {
+                    let delay = backoff(self.config.backoff(), attempt);
+                    log::debug!("Retrying {url} in {delay:?}: {err}");
+
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+
+    async fn fetch<T: DeserializeOwned>(&self, url: Url) -> reqwest::Result<T> {
+        self.client.get(url)
+            .header(header::ACCEPT, "application/json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    fn limit(&self, url: &Url) -> Arc<Semaphore> {
+        let mut limits = self.limits.lock().unwrap_or_else(|err| err.into_inner());
+
+        limits.entry(url.host_str().unwrap_or_default().to_owned())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.config.max_concurrency.max(1))))
+            .clone()
+    }
+}
+
+/// Whether trying again later could succeed. Malformed responses and client errors won't fix themselves.
+fn retryable(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+        None => err.is_timeout() || err.is_connect() || err.is_request() || err.is_body(),
+    }
+}
+
+/// `base` doubled for each previous retry, plus up to as much again at random so that clients don't retry in lockstep
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    let delay = base.saturating_mul(2u32.saturating_pow(attempt));
+    let jitter = fastrand::u64(..=delay.as_millis().min(u64::MAX.into()) as u64);
+
+    delay + Duration::from_millis(jitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles() {
+        let base = Duration::from_millis(100);
+
+        for attempt in 0..5 {
+            let delay = backoff(base, attempt);
+            let expected = base * 2u32.pow(attempt);
+
+            assert!(delay >= expected, "{delay:?} < {expected:?}");
+            assert!(delay <= expected * 2, "{delay:?} > {expected:?} * 2");
+        }
+    }
+
+    #[test]
+    fn backoff_without_base() {
+        assert_eq!(backoff(Duration::ZERO, 3), Duration::ZERO);
+    }
+}
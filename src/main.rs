@@ -1,26 +1,58 @@
 mod cache;
+mod error;
+mod hash;
+mod http;
+mod prefetch;
+mod snapshot;
+mod state;
+mod transit;
 mod v1;
 
 use std::io;
 use actix_web::{web, HttpResponse, Responder};
-use common::prelude::*;
+use error::Error;
+use error::Result;
 
 #[actix_web::main]
 pub async fn main() -> Result<()> {
     env_logger::init();
 
-    let config = common::get_config().await?;
+    let config = common::get_config().await
+        .map_err(|err| Error::Config(err.to_string()))?;
 
     log::debug!("Using config: {config:#?}");
 
+    let caches = state::Caches::new(&config.cache).await?;
+    caches.spawn_sweeper(&config.cache);
+
+    if let Some(dir) = &config.state.directory {
+        snapshot::restore(dir, &caches).await;
+        snapshot::spawn(dir.clone(), &config.state, caches.clone());
+    }
+
+    let http = http::HttpClient::new(&config.http)?;
+    let transit = transit::Transit::new(&config, &http)?;
+    let prefetcher = web::Data::from(prefetch::Prefetcher::spawn(&config, &caches, &transit, &http));
+
     let data = web::Data::new(config.clone());
+    let cache_data = web::Data::new(caches.clone());
+    let transit = web::Data::new(transit);
+    let http = web::Data::new(http);
     actix_web::HttpServer::new(move || actix_web::App::new()
         .app_data(data.clone())
+        .app_data(cache_data.clone())
+        .app_data(prefetcher.clone())
+        .app_data(transit.clone())
+        .app_data(http.clone())
         .service(v1::v1()))
         .bind(&config.bind.socket)?
         .run()
         .await?;
 
+    if let Some(dir) = &config.state.directory {
+        snapshot::save(dir, &caches).await?;
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,101 @@
+use crate::http::HttpClient;
+use crate::state::Caches;
+use crate::transit::Board;
+use crate::transit::Transit;
+use crate::v1::buses;
+use crate::v1::weather;
+use crate::Result;
+use common::config::Config;
+use common::prelude::tokio;
+use common::prelude::tokio::sync::RwLock;
+use common::prelude::tokio::time::MissedTickBehavior;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// Keeps the caches warm by refreshing every configured source in the background, so that handlers only ever have to
+/// read from them.
+#[derive(Default)]
+pub struct Prefetcher {
+    sources: RwLock<HashMap<String, SourceStatus>>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceStatus {
+    pub last_success: Option<SystemTime>,
+    pub last_attempt: Option<SystemTime>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl Prefetcher {
+    /// Starts a refresh loop for each configured stop and for the weather.
+    pub fn spawn(config: &Config, caches: &Caches, transit: &Transit, http: &HttpClient) -> Arc<Self> {
+        let prefetcher = Arc::new(Self::default());
+
+        let every = config.cache.departures.prefetch_interval();
+        for stop in &config.departure {
+            let board = Board {
+                stop: stop.point.clone(),
+                mode: stop.mode.unwrap_or_default(),
+                at: None,
+            };
+
+            let (caches, transit) = (caches.clone(), transit.clone());
+            prefetcher.clone().run(format!("departures:{stop}", stop = board.stop), every, move || {
+                let (caches, transit, board) = (caches.clone(), transit.clone(), board.clone());
+                async move { buses::refresh(&caches, &transit, board).await }
+            });
+        }
+
+        let every = config.cache.weather.prefetch_interval();
+        let (config, caches, http) = (config.clone(), caches.clone(), http.clone());
+        prefetcher.clone().run("weather".to_owned(), every, move || {
+            let (config, caches, http) = (config.clone(), caches.clone(), http.clone());
+            async move { weather::refresh(&caches, &http, &config).await }
+        });
+
+        prefetcher
+    }
+
+    pub async fn status(&self) -> HashMap<String, SourceStatus> {
+        self.sources.read().await.clone()
+    }
+
+    fn run<Fut>(self: Arc<Self>, source: String, every: Duration, refresh: impl Fn() -> Fut + Send + 'static)
+    where
+        Fut: Future<Output = Result<()>> + Send,
+    {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                let result = refresh().await;
+                let now = SystemTime::now();
+
+                let mut sources = self.sources.write().await;
+                let status = sources.entry(source.clone()).or_default();
+                status.last_attempt = Some(now);
+
+                match result {
+                    Ok(()) => {
+                        log::trace!("Prefetched {source}");
+                        status.last_success = Some(now);
+                        status.last_error = None;
+                    }
+                    Err(err) => {
+                        log::warn!("Failed to prefetch {source}: {err}");
+                        status.last_error = Some(err.to_string());
+                    }
+                }
+            }
+        });
+    }
+}
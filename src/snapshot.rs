@@ -0,0 +1,59 @@
+use crate::state::Caches;
+use crate::Result;
+use common::config::StateConfig;
+use common::prelude::tokio;
+use common::prelude::tokio::time::MissedTickBehavior;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Reloads the caches from the snapshots in `dir`, so that the dashboard has something to show even if the upstreams
+/// can't be reached right after a restart.
+pub async fn restore(dir: &Path, caches: &Caches) {
+    let restored = [
+        caches.departures.load(dir.join("departures.json")).await,
+        caches.weather.load(dir.join("weather.json")).await,
+        caches.city_names.load(dir.join("city-names.json")).await,
+        caches.trips.load(dir.join("trips.json")).await,
+        caches.lines.load(dir.join("lines.json")).await,
+    ];
+
+    for err in restored.into_iter().filter_map(|i| i.err()) {
+        log::warn!("Failed to restore cache snapshot: {err}");
+    }
+}
+
+pub async fn save(dir: &Path, caches: &Caches) -> Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    caches.departures.save(dir.join("departures.json")).await?;
+    caches.weather.save(dir.join("weather.json")).await?;
+    caches.city_names.save(dir.join("city-names.json")).await?;
+    caches.trips.save(dir.join("trips.json")).await?;
+    caches.lines.save(dir.join("lines.json")).await?;
+
+    log::trace!("Saved cache snapshot to {dir:?}");
+
+    Ok(())
+}
+
+/// Saves a snapshot every `snapshot_interval`.
+pub fn spawn(dir: PathBuf, config: &StateConfig, caches: Caches) {
+    let every = Duration::from_millis(config.snapshot_interval.into());
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // The first tick completes immediately, at which point there's nothing new worth saving
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            if let Err(err) = save(&dir, &caches).await {
+                log::warn!("Failed to save cache snapshot: {err}");
+            }
+        }
+    });
+}
@@ -0,0 +1,132 @@
+use crate::Result;
+use crate::cache::Cache;
+use crate::cache::CacheStats;
+use crate::cache::MemoryBackend;
+use crate::cache::RedisBackend;
+use crate::transit::Board;
+use crate::transit::Route;
+use crate::v1::buses_schema::DepartureBoardStop;
+use crate::v1::trips_schema::Journey;
+use crate::v1::weather::Coordinate;
+use crate::v1::weather::ForecastParams;
+use crate::v1::weather_schema::WeatherState;
+use common::config::CacheConfig;
+use common::config::CachePolicy;
+use common::config::RedisConfig;
+use common::prelude::tokio;
+use common::prelude::tokio::time::MissedTickBehavior;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Every cache the API serves from, sized according to the `[cache]` config section.
+#[derive(Clone)]
+pub struct Caches {
+    pub departures: Cache<Board, Vec<DepartureBoardStop>>,
+    pub weather: Cache<ForecastParams, WeatherState>,
+    pub city_names: Cache<Coordinate, String>,
+    pub trips: Cache<Route, Vec<Journey>>,
+    pub lines: Cache<String, Vec<String>>,
+}
+
+impl Caches {
+    /// Connects to Redis first if `[cache.redis]` is configured, otherwise values are kept in memory.
+    pub async fn new(config: &CacheConfig) -> Result<Self> {
+        async fn cache<Key, Value>(name: &str, policy: &CachePolicy, redis: Option<&RedisConfig>) -> Result<Cache<Key, Value>>
+        where
+            Key: Hash + Eq + Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+            Value: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+        {
+            Ok(match redis {
+                Some(redis) => {
+                    let backend = RedisBackend::connect(&redis.url, format!("{prefix}:{name}", prefix = redis.prefix)).await?;
+                    Cache::new(backend, policy.lifetime(), policy.max_stale())
+                }
+                None => Cache::new(MemoryBackend::new(policy.max_entries), policy.lifetime(), policy.max_stale()),
+            })
+        }
+
+        let redis = config.redis.as_ref();
+
+        Ok(Self {
+            departures: cache("departures", &config.departures, redis).await?,
+            weather: cache("weather", &config.weather, redis).await?,
+            city_names: cache("cityNames", &config.city_names, redis).await?,
+            trips: cache("trips", &config.trips, redis).await?,
+            lines: cache("lines", &config.lines, redis).await?,
+        })
+    }
+
+    pub async fn stats(&self) -> Result<BTreeMap<&'static str, CacheStats>> {
+        Ok(BTreeMap::from([
+            ("departures", self.departures.stats().await?),
+            ("weather", self.weather.stats().await?),
+            ("cityNames", self.city_names.stats().await?),
+            ("trips", self.trips.stats().await?),
+            ("lines", self.lines.stats().await?),
+        ]))
+    }
+
+    /// Empties the cache listed as `name` in [`Caches::stats`]. Returns `false` if there is no such cache.
+    pub async fn clear(&self, name: &str) -> Result<bool> {
+        match name {
+            "departures" => self.departures.clear().await?,
+            "weather" => self.weather.clear().await?,
+            "cityNames" => self.city_names.clear().await?,
+            "trips" => self.trips.clear().await?,
+            "lines" => self.lines.clear().await?,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Removes a single value from the cache listed as `name`. Returns whether it was present.
+    pub async fn invalidate(&self, name: &str, key: &str) -> Result<bool> {
+        match name {
+            "departures" => self.departures.invalidate(key).await,
+            "weather" => self.weather.invalidate(key).await,
+            "cityNames" => self.city_names.invalidate(key).await,
+            "trips" => self.trips.invalidate(key).await,
+            "lines" => self.lines.invalidate(key).await,
+            _ => Ok(false),
+        }
+    }
+
+    /// Periodically removes values which have become too old to be served.
+    pub fn spawn_sweeper(&self, config: &CacheConfig) {
+        let caches = self.clone();
+        let every = Duration::from_millis(config.sweep_interval.into());
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                let swept = [
+                    caches.departures.sweep().await,
+                    caches.weather.sweep().await,
+                    caches.city_names.sweep().await,
+                    caches.trips.sweep().await,
+                    caches.lines.sweep().await,
+                ];
+
+                let mut total = 0;
+                for swept in swept {
+                    match swept {
+                        Ok(swept) => total += swept,
+                        Err(err) => log::warn!("Failed to sweep cache: {err}"),
+                    }
+                }
+
+                if total > 0 {
+                    log::debug!("Swept {total} expired cache values");
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,103 @@
+use crate::v1::buses_schema::DepartureBoardStop;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Remembers when each disruption notice first showed up, for as long as any board still reports it.
+#[derive(Default)]
+pub struct Disruptions {
+    seen: Mutex<HashMap<String, Seen>>,
+}
+
+struct Seen {
+    first: SystemTime,
+    boards: HashSet<String>,
+}
+
+impl Disruptions {
+    /// Takes note of the notices in a freshly fetched board. Notices which `board` no longer reports are forgotten once
+    /// no other board reports them either.
+    pub fn record(&self, board: &str, departures: &[DepartureBoardStop]) {
+        let current = departures.iter()
+            .flat_map(|departure| &departure.hints)
+            .map(|hint| hint.id.as_str())
+            .collect::<HashSet<_>>();
+
+        let now = SystemTime::now();
+        let mut seen = self.seen.lock().unwrap_or_else(|err| err.into_inner());
+
+        for id in &current {
+            seen.entry(id.to_string())
+                .or_insert_with(|| Seen { first: now, boards: HashSet::new() })
+                .boards
+                .insert(board.to_owned());
+        }
+
+        seen.retain(|id, seen| {
+            if !current.contains(id.as_str()) {
+                seen.boards.remove(board);
+            }
+
+            !seen.boards.is_empty()
+        });
+    }
+
+    /// When the notice `id` was first reported, if it is still current
+    pub fn first_seen(&self, id: &str) -> Option<SystemTime> {
+        self.seen.lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(id)
+            .map(|seen| seen.first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::buses_schema::DepartureHint;
+
+    fn board(hints: &[&DepartureHint]) -> Vec<DepartureBoardStop> {
+        hints.iter()
+            .map(|hint| {
+                let mut departure = DepartureBoardStop::example("5", "Hbf", "2030-01-02T14:00:00+01:00", "2030-01-02T14:00:00+01:00");
+                departure.hints.push((*hint).clone());
+                departure
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_first_seen() {
+        let works = DepartureHint::new("Bauarbeiten");
+        let disruptions = Disruptions::default();
+
+        disruptions.record("a", &board(&[&works]));
+        let first = disruptions.first_seen(&works.id).expect("Notice wasn't recorded");
+
+        disruptions.record("a", &board(&[&works]));
+        disruptions.record("b", &board(&[&works]));
+        assert_eq!(disruptions.first_seen(&works.id), Some(first));
+    }
+
+    #[test]
+    fn forgets_once_no_board_reports_it() {
+        let works = DepartureHint::new("Bauarbeiten");
+        let disruptions = Disruptions::default();
+
+        disruptions.record("a", &board(&[&works]));
+        disruptions.record("b", &board(&[&works]));
+
+        disruptions.record("a", &board(&[]));
+        assert!(disruptions.first_seen(&works.id).is_some(), "Forgotten whilst `b` still reports it");
+
+        disruptions.record("b", &board(&[]));
+        assert_eq!(disruptions.first_seen(&works.id), None);
+    }
+
+    #[test]
+    fn hint_ids_are_stable() {
+        assert_eq!(DepartureHint::new(" Bauarbeiten\n").id, DepartureHint::new("Bauarbeiten").id);
+        assert_eq!(DepartureHint::new("Bauarbeiten").id, format!("{hash:016x}", hash = crate::hash::fnv1a(b"Bauarbeiten")));
+    }
+}
@@ -0,0 +1,703 @@
+use crate::http::HttpClient;
+use crate::transit::Board;
+use crate::transit::Route;
+use crate::transit::TransitProvider;
+use crate::v1::buses_schema::BusSchema;
+use crate::v1::buses_schema::DepartureBoardStop;
+use crate::v1::buses_schema::DepartureHint;
+use crate::v1::buses_schema::DepartureList;
+use crate::v1::buses_schema::DepartureStatus;
+use crate::v1::buses_schema::Platform;
+use crate::v1::buses_schema::RealDateTimeClass;
+use crate::v1::buses_schema::TransportMode;
+use crate::v1::stops_schema::Stop;
+use crate::v1::trips_schema::EfaLeg;
+use crate::v1::trips_schema::EfaLegPoint;
+use crate::v1::trips_schema::Journey;
+use crate::v1::trips_schema::Leg;
+use crate::v1::trips_schema::Stopover;
+use crate::v1::trips_schema::TripSchema;
+use crate::Error;
+use crate::Result;
+use async_trait::async_trait;
+use common::efa::CoordParams;
+use common::efa::CoordResponse;
+use common::efa::LinesParams;
+use common::efa::LinesResponse;
+use common::efa::SearchParams;
+use common::efa::StopResponse;
+use chrono::DateTime;
+use chrono::FixedOffset;
+use chrono::LocalResult;
+use chrono::NaiveDateTime;
+use chrono::Offset;
+use chrono::TimeDelta;
+use chrono::TimeZone;
+use chrono::Utc;
+use chrono_tz::Tz;
+use reqwest::Url;
+use serde::Serialize;
+
+/// EFA reports cancelled trips with this delay
+const CANCELLED: i64 = -9999;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Query {
+    #[serde(rename = "outputFormat")]
+    output_format: &'static str,
+
+    mode: &'static str,
+
+    #[serde(rename = "useProxFootSearch")]
+    include_proximate: u8,
+
+    #[serde(rename = "name_dm")]
+    stop_id: String,
+
+    #[serde(rename = "itdDate", skip_serializing_if = "Option::is_none")]
+    date: Option<String>,
+
+    #[serde(rename = "itdTime", skip_serializing_if = "Option::is_none")]
+    time: Option<String>,
+
+    limit: usize,
+
+    #[serde(rename = "itdDateTimeDepArr")]
+    depart_or_arrive: &'static str,
+
+    type_dm: &'static str,
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self {
+            output_format: "JSON",
+            mode: "direct",
+            include_proximate: 1,
+            stop_id: "".to_string(),
+            date: None,
+            time: None,
+            limit: 50,
+            depart_or_arrive: "dep",
+            type_dm: "any",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TripQuery {
+    #[serde(rename = "outputFormat")]
+    output_format: &'static str,
+
+    type_origin: &'static str,
+    name_origin: String,
+
+    type_destination: &'static str,
+    name_destination: String,
+
+    #[serde(rename = "calcNumberOfTrips")]
+    connections: usize,
+
+    #[serde(rename = "itdTripDateTimeDepArr")]
+    depart_or_arrive: &'static str,
+
+    #[serde(rename = "useRealtime")]
+    realtime: u8,
+
+    #[serde(rename = "locationServerActive")]
+    location_server: u8,
+}
+
+impl Default for TripQuery {
+    fn default() -> Self {
+        Self {
+            output_format: "JSON",
+            type_origin: "any",
+            name_origin: "".to_string(),
+            type_destination: "any",
+            name_destination: "".to_string(),
+            connections: 3,
+            depart_or_arrive: "dep",
+            realtime: 1,
+            location_server: 1,
+        }
+    }
+}
+
+/// Departure monitor, trip and stop finder requests against an EFA instance
+pub struct EfaProvider {
+    client: HttpClient,
+    dm_url: Url,
+    trip_url: Url,
+    sf_url: Url,
+    coord_url: Url,
+
+    /// Zone the instance gives its local times in
+    timezone: Tz,
+}
+
+impl EfaProvider {
+    /// `base` is the URL under which the instance's `XML_DM_REQUEST` and friends are found.
+    pub fn new(base: &str, timezone: Tz, client: HttpClient) -> Result<Self> {
+        let mut base = Url::parse(base)
+            .map_err(|err| Error::Config(format!("Invalid EFA URL '{base}': {err}")))?;
+
+        // Without a trailing slash, joining would replace the last segment of the path rather than append to it
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{path}/", path = base.path()));
+        }
+
+        Ok(Self {
+            client,
+            dm_url: base.join("XML_DM_REQUEST").map_err(Error::internal)?,
+            trip_url: base.join("XML_TRIP_REQUEST2").map_err(Error::internal)?,
+            sf_url: base.join("XML_STOPFINDER_REQUEST").map_err(Error::internal)?,
+            coord_url: base.join("XML_COORD_REQUEST").map_err(Error::internal)?,
+            timezone,
+        })
+    }
+}
+
+#[async_trait]
+impl TransitProvider for EfaProvider {
+    async fn departures(&self, board: &Board) -> Result<Vec<DepartureBoardStop>> {
+        let mut uri = self.dm_url.clone();
+
+        let query = serde_qs::to_string(&Query {
+            stop_id: board.stop.clone(),
+            depart_or_arrive: board.mode.as_str(),
+            date: board.at.map(|at| at.with_timezone(&self.timezone).format("%Y%m%d").to_string()),
+            time: board.at.map(|at| at.with_timezone(&self.timezone).format("%H%M").to_string()),
+            ..Default::default()
+        })
+        .map_err(Error::internal)?;
+
+        uri.set_query(Some(&query));
+
+        log::debug!("Bus URL: {uri:?}", uri = uri.to_string());
+
+        let res: BusSchema = self.client.get(uri).await?;
+
+        departures(res, self.timezone)
+    }
+
+    async fn trips(&self, route: &Route) -> Result<Vec<Journey>> {
+        let mut uri = self.trip_url.clone();
+
+        let query = serde_qs::to_string(&TripQuery {
+            name_origin: route.origin.clone(),
+            name_destination: route.destination.clone(),
+            connections: route.connections,
+            ..Default::default()
+        })
+        .map_err(Error::internal)?;
+
+        uri.set_query(Some(&query));
+
+        log::debug!("Trip URL: {uri:?}", uri = uri.to_string());
+
+        let res: TripSchema = self.client.get(uri).await?;
+
+        let mut journeys = journeys(res, self.timezone)?;
+        journeys.truncate(route.connections);
+
+        Ok(journeys)
+    }
+
+    async fn search(&self, search: &str, matches: u32) -> Result<Vec<Stop>> {
+        let params = SearchParams::default().search(search).matches(matches);
+        let res: StopResponse = self.client.get(with_query(&self.sf_url, &params)?).await?;
+
+        Ok(res.stops.points.iter()
+            .map(|point| {
+                let coordinates = point.coordinates();
+
+                Stop {
+                    name: point.name.clone(),
+                    gid: point.ids.gid.clone(),
+                    place: point.ids.place.clone(),
+                    latitude: coordinates.map(|(latitude, _)| latitude),
+                    longitude: coordinates.map(|(_, longitude)| longitude),
+                    distance: None,
+                    lines: Vec::new(),
+                }
+            })
+            .collect())
+    }
+
+    async fn nearby(&self, latitude: f64, longitude: f64, radius: u32, matches: u32) -> Result<Vec<Stop>> {
+        let params = CoordParams::default().around(latitude, longitude).radius(radius).matches(matches);
+        let res: CoordResponse = self.client.get(with_query(&self.coord_url, &params)?).await?;
+
+        let mut pins = res.pins;
+        pins.sort_by_key(|pin| pin.distance().unwrap_or(u32::MAX));
+        pins.truncate(matches as usize);
+
+        Ok(pins.iter()
+            .map(|pin| {
+                let point = pin.point();
+                let coordinates = pin.coordinates();
+
+                Stop {
+                    name: point.name,
+                    gid: point.ids.gid,
+                    place: point.ids.place,
+                    latitude: coordinates.map(|(latitude, _)| latitude),
+                    longitude: coordinates.map(|(_, longitude)| longitude),
+                    distance: pin.distance(),
+                    lines: Vec::new(),
+                }
+            })
+            .collect())
+    }
+
+    /// According to the departure monitor
+    async fn lines(&self, stop: &str) -> Result<Vec<String>> {
+        let res: LinesResponse = self.client.get(with_query(&self.dm_url, &LinesParams::new(stop))?).await?;
+
+        Ok(res.lines())
+    }
+}
+
+/// `url` with `params` form-encoded as its query
+fn with_query(url: &Url, params: &impl Serialize) -> Result<Url> {
+    let mut url = url.clone();
+    url.set_query(Some(&serde_urlencoded::to_string(params).map_err(Error::internal)?));
+
+    Ok(url)
+}
+
+fn departures(res: BusSchema, timezone: Tz) -> Result<Vec<DepartureBoardStop>> {
+    res.departure_list
+        .iter()
+        .map(|line| {
+            let given_eta = parse_date_time(
+                line.date_time
+                    .clone(),
+                timezone,
+                None,
+            )?;
+
+            let eta = match &line.real_date_time {
+                Some(real_date_time) => parse_date_time(real_date_time.clone(), timezone, Some(given_eta))?,
+                None => given_eta,
+            };
+
+            let (status, delay_minutes) = status(line, (eta - given_eta).num_minutes());
+
+            Ok(DepartureBoardStop {
+                stop: line.stop_name.clone(),
+                line: line.serving_line.symbol.to_string(),
+                direction: line.serving_line.direction.to_string(),
+                platform: platform(&line.platform, &line.platform_name),
+                mode: transport_mode(&line.serving_line.mot_type),
+                operator: Some(line.operator.name.clone()).filter(|name| !name.is_empty()),
+                train_number: Some(line.serving_line.train_num.clone()).filter(|number| !number.is_empty()),
+                expected_arrival: eta,
+                given_arrival: given_eta,
+                delay_minutes,
+                is_realtime: line.serving_line.realtime == "1",
+                status,
+                reach: None,
+                leave_in_minutes: None,
+                hints: line.serving_line.hints.iter()
+                    .flatten()
+                    .filter(|hint| !hint.content.trim().is_empty())
+                    .map(|hint| DepartureHint::new(&hint.content))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+fn journeys(res: TripSchema, timezone: Tz) -> Result<Vec<Journey>> {
+    res.trips
+        .unwrap_or_default()
+        .iter()
+        .map(|trip| {
+            let legs = trip.legs.iter()
+                .map(|i| leg(i, timezone))
+                .collect::<Result<Vec<_>>>()?;
+
+            let (Some(first), Some(last)) = (legs.first(), legs.last()) else {
+                return Err(Error::SchemaMismatch("Trip without legs".to_owned()));
+            };
+
+            let (departure, arrival) = (first.origin.expected, last.destination.expected);
+
+            Ok(Journey {
+                departure,
+                arrival,
+                duration_minutes: (arrival - departure).num_minutes(),
+                transfers: legs.iter()
+                    .filter(|leg| leg.mode != TransportMode::Walk)
+                    .count()
+                    .saturating_sub(1),
+                legs,
+            })
+        })
+        .collect()
+}
+
+fn leg(leg: &EfaLeg, timezone: Tz) -> Result<Leg> {
+    let origin = leg.points.iter()
+        .find(|point| point.usage == "departure")
+        .or(leg.points.first());
+
+    let destination = leg.points.iter()
+        .find(|point| point.usage == "arrival")
+        .or(leg.points.last());
+
+    let (Some(origin), Some(destination)) = (origin, destination) else {
+        return Err(Error::SchemaMismatch("Leg without stops".to_owned()));
+    };
+
+    let is_realtime = origin.date_time.rt_time.is_some();
+    let (origin, destination) = (stopover(origin, timezone)?, stopover(destination, timezone)?);
+
+    Ok(Leg {
+        mode: transport_mode(&leg.mode.mot_type),
+        line: [&leg.mode.symbol, &leg.mode.number].into_iter()
+            .find(|line| !line.is_empty())
+            .cloned(),
+        direction: Some(leg.mode.destination.clone()).filter(|direction| !direction.is_empty()),
+        delay_minutes: is_realtime.then(|| (origin.expected - origin.planned).num_minutes()),
+        is_realtime,
+        cancelled: leg.realtime_status.as_deref().is_some_and(|status| status.contains("TRIP_CANCELLED")),
+        origin,
+        destination,
+    })
+}
+
+fn stopover(point: &EfaLegPoint, timezone: Tz) -> Result<Stopover> {
+    let time = &point.date_time;
+    let planned = parse_trip_time(&time.date, &time.time, timezone, None)?;
+
+    let expected = match &time.rt_time {
+        Some(rt_time) => parse_trip_time(time.rt_date.as_deref().unwrap_or(&time.date), rt_time, timezone, Some(planned))?,
+        None => planned,
+    };
+
+    Ok(Stopover {
+        name: point.name.clone(),
+        platform: platform(&point.point_ref.platform, &point.platform_name),
+        planned,
+        expected,
+    })
+}
+
+fn platform(code: &str, name: &str) -> Option<Platform> {
+    match (code, name) {
+        ("", "") => None,
+        ("", name) => Some(Platform { code: name.to_owned(), name: name.to_owned() }),
+        (code, "") => Some(Platform { code: code.to_owned(), name: code.to_owned() }),
+        (code, name) => Some(Platform { code: code.to_owned(), name: name.to_owned() }),
+    }
+}
+
+/// Maps EFA's `motType` codes, which are the same across instances
+fn transport_mode(mot_type: &str) -> TransportMode {
+    match mot_type.trim() {
+        "0" => TransportMode::Train,
+        "1" => TransportMode::SuburbanRailway,
+        "2" => TransportMode::Underground,
+        "3" => TransportMode::LightRail,
+        "4" => TransportMode::Tram,
+        "5" => TransportMode::CityBus,
+        "6" => TransportMode::RegionalBus,
+        "7" => TransportMode::ExpressBus,
+        "8" => TransportMode::CableCar,
+        "9" => TransportMode::Ferry,
+        "10" => TransportMode::OnDemand,
+        "12" => TransportMode::Plane,
+        "13" => TransportMode::RegionalTrain,
+        "14" => TransportMode::NationalTrain,
+        "15" => TransportMode::InternationalTrain,
+        "16" => TransportMode::HighSpeedTrain,
+        "17" => TransportMode::RailReplacementBus,
+        "18" => TransportMode::ShuttleTrain,
+        "19" => TransportMode::CommunityBus,
+        "99" | "100" => TransportMode::Walk,
+        _ => TransportMode::Other,
+    }
+}
+
+/// Works out what became of a departure from its realtime fields. `drift` is how far the realtime departure is from
+/// the timetabled one, used in case EFA doesn't report a delay itself.
+fn status(line: &DepartureList, drift: i64) -> (DepartureStatus, Option<i64>) {
+    let trip_status = line.realtime_trip_status.as_deref().unwrap_or_default();
+    let realtime = line.serving_line.realtime == "1";
+
+    let delay = line.serving_line.delay.as_deref()
+        .and_then(|delay| delay.trim().parse::<i64>().ok())
+        .or(line.real_date_time.as_ref().map(|_| drift))
+        .filter(|_| realtime);
+
+    if trip_status.contains("TRIP_CANCELLED") || delay == Some(CANCELLED) {
+        return (DepartureStatus::Cancelled, None);
+    }
+
+    if trip_status.contains("EXTRA_TRIP") {
+        return (DepartureStatus::ExtraTrip, delay);
+    }
+
+    match delay {
+        None => (DepartureStatus::NoRealtime, None),
+        Some(delay) if delay > 0 => (DepartureStatus::Delayed, Some(delay)),
+        Some(delay) => (DepartureStatus::OnTime, Some(delay)),
+    }
+}
+
+fn parse_date_time(date: RealDateTimeClass, timezone: Tz, near: Option<DateTime<FixedOffset>>) -> Result<DateTime<FixedOffset>> {
+    let (year, month, day, hour, minute) = (
+        date.year.parse::<i32>()?,
+        date.month.parse::<u32>()?,
+        date.day.parse::<u32>()?,
+        date.hour.parse::<u32>()?,
+        date.minute.parse::<u32>()?,
+    );
+
+    let date = chrono::NaiveDate::from_ymd_opt(year, month, day);
+    let time = chrono::NaiveTime::from_hms_opt(hour, minute, 0);
+
+    let datetime = date
+        .and_then(|date| Some(date.and_time(time?)))
+        .ok_or(Error::SchemaMismatch("No time provided".to_owned()))?;
+
+    localise(datetime, timezone, near)
+}
+
+/// Trip responses give times as `dd.mm.yyyy` and `hh:mm`
+fn parse_trip_time(date: &str, time: &str, timezone: Tz, near: Option<DateTime<FixedOffset>>) -> Result<DateTime<FixedOffset>> {
+    let datetime = NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%d.%m.%Y %H:%M")?;
+
+    localise(datetime, timezone, near)
+}
+
+/// Pins EFA's wall clock time down in `timezone`.
+///
+/// When the clocks go back, the repeated hour is ambiguous. The reading closest to `near` is chosen then, so that a
+/// realtime departure ends up next to its timetabled one, or the earlier reading without a hint. Times which are
+/// skipped when the clocks go forward are read with the offset from before the gap, i.e. moved forward by it.
+fn localise(datetime: NaiveDateTime, timezone: Tz, near: Option<DateTime<FixedOffset>>) -> Result<DateTime<FixedOffset>> {
+    match timezone.from_local_datetime(&datetime) {
+        LocalResult::Single(datetime) => Ok(datetime.fixed_offset()),
+        LocalResult::Ambiguous(earlier, later) => {
+            let prefer_later = near.is_some_and(|near| {
+                later.signed_duration_since(near).abs() < earlier.signed_duration_since(near).abs()
+            });
+
+            Ok(if prefer_later { later } else { earlier }.fixed_offset())
+        }
+        LocalResult::None => {
+            let before = timezone.offset_from_utc_datetime(&(datetime - TimeDelta::days(1))).fix();
+            let utc = datetime - TimeDelta::seconds(before.local_minus_utc().into());
+
+            Ok(Utc.from_utc_datetime(&utc).with_timezone(&timezone).fixed_offset())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A departure monitor response from efa-bw.de, trimmed to a handful of departures
+    const DEPARTURES: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/efa/departures.json"));
+
+    /// A trip response with one connection involving a footpath and one direct, cancelled train
+    const TRIPS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/efa/trips.json"));
+
+    fn fixture() -> Vec<DepartureBoardStop> {
+        let res: BusSchema = serde_json::from_str(DEPARTURES).expect("Fixture doesn't match the schema");
+        departures(res, chrono_tz::Europe::Berlin).expect("Failed to convert fixture")
+    }
+
+    #[test]
+    fn status() {
+        let statuses = fixture().iter()
+            .map(|departure| (departure.line.clone(), departure.status))
+            .collect::<Vec<_>>();
+
+        assert_eq!(statuses, [
+            ("5".to_owned(), DepartureStatus::OnTime),
+            ("7".to_owned(), DepartureStatus::Delayed),
+            ("3".to_owned(), DepartureStatus::Cancelled),
+            ("E".to_owned(), DepartureStatus::ExtraTrip),
+            ("826".to_owned(), DepartureStatus::NoRealtime),
+            ("RB 63".to_owned(), DepartureStatus::Delayed),
+        ]);
+    }
+
+    #[test]
+    fn delay() {
+        let delays = fixture().iter()
+            .map(|departure| departure.delay_minutes)
+            .collect::<Vec<_>>();
+
+        assert_eq!(delays, [Some(0), Some(4), None, Some(0), None, Some(1)]);
+    }
+
+    #[test]
+    fn realtime() {
+        let realtime = fixture().iter()
+            .map(|departure| departure.is_realtime)
+            .collect::<Vec<_>>();
+
+        assert_eq!(realtime, [true, true, true, true, false, true]);
+    }
+
+    #[test]
+    fn platform() {
+        let departures = fixture();
+
+        assert_eq!(departures[0].platform, Some(Platform {
+            code: "A".to_owned(),
+            name: "Bussteig A".to_owned(),
+        }));
+        assert_eq!(departures[5].platform.as_ref().map(|platform| platform.code.as_str()), Some("2"));
+    }
+
+    #[test]
+    fn mode() {
+        let modes = fixture().iter()
+            .map(|departure| departure.mode)
+            .collect::<Vec<_>>();
+
+        assert_eq!(modes[0], TransportMode::CityBus);
+        assert_eq!(modes[4], TransportMode::RegionalBus);
+        assert_eq!(modes[5], TransportMode::RegionalTrain);
+        assert_eq!(transport_mode("11"), TransportMode::Other);
+        assert_eq!(transport_mode("-1"), TransportMode::Other);
+    }
+
+    #[test]
+    fn operator_and_train_number() {
+        let departures = fixture();
+
+        assert_eq!(departures[1].operator.as_deref(), Some("RAB Regionalbusverkehr"));
+        assert_eq!(departures[0].train_number, None);
+        assert_eq!(departures[5].train_number.as_deref(), Some("17534"));
+    }
+
+    #[test]
+    fn arrivals() {
+        let res: BusSchema = serde_json::from_str(&DEPARTURES.replace("departureList", "arrivalList"))
+            .expect("Arrivals don't match the schema");
+
+        assert_eq!(departures(res, chrono_tz::Europe::Berlin).expect("Failed to convert arrivals").len(), fixture().len());
+    }
+
+    #[test]
+    fn expected_arrival() {
+        let departures = fixture();
+
+        let delayed = &departures[1];
+        assert_eq!((delayed.expected_arrival - delayed.given_arrival).num_minutes(), 4);
+
+        // Cancelled and timetable-only departures have no realtime departure, so the timetabled one is expected
+        for departure in [&departures[2], &departures[4]] {
+            assert_eq!(departure.expected_arrival, departure.given_arrival);
+        }
+    }
+
+    fn trip_fixture() -> Vec<Journey> {
+        let res: TripSchema = serde_json::from_str(TRIPS).expect("Fixture doesn't match the schema");
+        journeys(res, chrono_tz::Europe::Berlin).expect("Failed to convert fixture")
+    }
+
+    #[test]
+    fn transfers() {
+        let journeys = trip_fixture();
+
+        // The footpath between the two buses isn't a transfer of its own
+        assert_eq!(journeys.iter().map(|journey| journey.transfers).collect::<Vec<_>>(), vec![1, 0]);
+        assert_eq!(journeys[0].legs[1].mode, TransportMode::Walk);
+        assert_eq!(journeys[0].legs[1].line, None);
+    }
+
+    #[test]
+    fn trip_times() {
+        let journeys = trip_fixture();
+
+        let bus = &journeys[0].legs[0];
+        assert!(bus.is_realtime);
+        assert_eq!(bus.delay_minutes, Some(2));
+        assert_eq!(journeys[0].departure, bus.origin.expected);
+        assert_eq!(journeys[0].duration_minutes, 25);
+
+        let footpath = &journeys[0].legs[1];
+        assert!(!footpath.is_realtime);
+        assert_eq!(footpath.delay_minutes, None);
+    }
+
+    #[test]
+    fn trip_legs() {
+        let journeys = trip_fixture();
+
+        let train = &journeys[1].legs[0];
+        assert_eq!(train.mode, TransportMode::RegionalTrain);
+        assert_eq!(train.line.as_deref(), Some("RB 63"));
+        assert_eq!(train.direction.as_deref(), Some("Reutlingen Hbf"));
+        assert_eq!(train.origin.platform, Some(Platform { code: "2".to_owned(), name: "Gleis 2".to_owned() }));
+        assert!(train.cancelled);
+        assert!(!journeys[0].legs[0].cancelled);
+    }
+
+    #[test]
+    fn no_trips() {
+        let res: TripSchema = serde_json::from_str(r#"{"trips": null}"#).expect("Empty response doesn't match the schema");
+        assert!(journeys(res, chrono_tz::Europe::Berlin).expect("Failed to convert empty response").is_empty());
+    }
+
+    fn berlin(date: &str, time: &str, near: Option<&str>) -> DateTime<FixedOffset> {
+        let near = near.map(|near| DateTime::parse_from_rfc3339(near).expect("Invalid hint"));
+        parse_trip_time(date, time, chrono_tz::Europe::Berlin, near).expect("Failed to parse time")
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(berlin("19.01.2026", "08:12", None).to_rfc3339(), "2026-01-19T08:12:00+01:00");
+        assert_eq!(berlin("19.10.2026", "08:12", None).to_rfc3339(), "2026-10-19T08:12:00+02:00");
+
+        // The zone is configured rather than taken from the environment
+        let tokyo = parse_trip_time("19.10.2026", "08:12", chrono_tz::Asia::Tokyo, None).expect("Failed to parse time");
+        assert_eq!(tokyo.to_rfc3339(), "2026-10-19T08:12:00+09:00");
+    }
+
+    #[test]
+    fn rfc3339_output() {
+        let departure = serde_json::to_value(&fixture()[0]).expect("Failed to serialize departure");
+        assert_eq!(departure["givenArrival"], "2026-10-19T08:12:00+02:00");
+    }
+
+    #[test]
+    fn clocks_go_forward() {
+        // 02:00 to 03:00 doesn't exist on the 29th of March 2026
+        assert_eq!(berlin("29.03.2026", "01:59", None).to_rfc3339(), "2026-03-29T01:59:00+01:00");
+        assert_eq!(berlin("29.03.2026", "02:30", None).to_rfc3339(), "2026-03-29T03:30:00+02:00");
+        assert_eq!(berlin("29.03.2026", "03:00", None).to_rfc3339(), "2026-03-29T03:00:00+02:00");
+    }
+
+    #[test]
+    fn clocks_go_back() {
+        // 02:00 to 03:00 happens twice on the 25th of October 2026
+        assert_eq!(berlin("25.10.2026", "01:59", None).to_rfc3339(), "2026-10-25T01:59:00+02:00");
+        assert_eq!(berlin("25.10.2026", "02:30", None).to_rfc3339(), "2026-10-25T02:30:00+02:00");
+        assert_eq!(berlin("25.10.2026", "03:00", None).to_rfc3339(), "2026-10-25T03:00:00+01:00");
+
+        // Either reading is taken if it's closer to the hint
+        assert_eq!(berlin("25.10.2026", "02:30", Some("2026-10-25T02:25:00+01:00")).to_rfc3339(), "2026-10-25T02:30:00+01:00");
+        assert_eq!(berlin("25.10.2026", "02:30", Some("2026-10-25T02:25:00+02:00")).to_rfc3339(), "2026-10-25T02:30:00+02:00");
+    }
+
+    #[test]
+    fn delay_across_clocks_going_back() {
+        // Timetabled just before the clocks go back, leaving ten minutes late, i.e. after they did
+        let planned = berlin("25.10.2026", "02:55", None);
+        let expected = berlin("25.10.2026", "02:05", Some(&planned.to_rfc3339()));
+
+        assert_eq!(expected.to_rfc3339(), "2026-10-25T02:05:00+01:00");
+        assert_eq!((expected - planned).num_minutes(), 10);
+    }
+}
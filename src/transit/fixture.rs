@@ -0,0 +1,48 @@
+use crate::transit::Board;
+use crate::transit::Route;
+use crate::transit::TransitProvider;
+use crate::v1::buses_schema::DepartureBoardStop;
+use crate::v1::trips_schema::Journey;
+use crate::Result;
+use async_trait::async_trait;
+use common::config::BoardMode;
+use common::prelude::tokio;
+use std::path::PathBuf;
+
+/// Reads departures from `<directory>/<stop>.json`, arrivals from `<directory>/<stop>.arrivals.json` and journeys from
+/// `<directory>/<stop>.trips.<destination>.json`, in the same shape as the API returns them. This allows working on the
+/// dashboard, and testing the handlers, without depending on an upstream. The tests use `tests/fixtures/api`.
+pub struct FixtureProvider {
+    directory: PathBuf,
+}
+
+impl FixtureProvider {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl TransitProvider for FixtureProvider {
+    async fn departures(&self, board: &Board) -> Result<Vec<DepartureBoardStop>> {
+        let file = match board.mode {
+            BoardMode::Departures => format!("{stop}.json", stop = board.stop),
+            BoardMode::Arrivals => format!("{stop}.arrivals.json", stop = board.stop),
+        };
+
+        let fixture = tokio::fs::read(self.directory.join(file)).await?;
+        Ok(serde_json::from_slice(&fixture)?)
+    }
+
+    async fn trips(&self, route: &Route) -> Result<Vec<Journey>> {
+        let file = format!("{origin}.trips.{destination}.json", origin = route.origin, destination = route.destination);
+
+        let fixture = tokio::fs::read(self.directory.join(file)).await?;
+        let mut journeys: Vec<Journey> = serde_json::from_slice(&fixture)?;
+        journeys.truncate(route.connections);
+
+        Ok(journeys)
+    }
+}
@@ -0,0 +1,178 @@
+mod disruptions;
+mod efa;
+mod fixture;
+
+pub use disruptions::Disruptions;
+pub use efa::EfaProvider;
+pub use fixture::FixtureProvider;
+
+use crate::http::HttpClient;
+use crate::v1::buses_schema::DepartureBoardStop;
+use crate::v1::stops_schema::Stop;
+use crate::v1::trips_schema::Journey;
+use crate::Error;
+use crate::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde::Serialize;
+use common::config::BoardMode;
+use common::config::Config;
+use common::config::EFA_API;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A source of departures. Whatever the upstream returns is normalised into [`DepartureBoardStop`]s, so that the
+/// handlers don't need to know where they came from.
+#[async_trait]
+pub trait TransitProvider: Send + Sync {
+    async fn departures(&self, board: &Board) -> Result<Vec<DepartureBoardStop>>;
+
+    /// The next connections along `route`. Not every provider can plan journeys.
+    async fn trips(&self, _route: &Route) -> Result<Vec<Journey>> {
+        Err(Error::Config("Journey planning isn't supported by this provider".to_owned()))
+    }
+
+    /// Up to `matches` stops whose name matches `search`, best matches first
+    async fn search(&self, _search: &str, _matches: u32) -> Result<Vec<Stop>> {
+        Err(Error::Config("Searching for stops isn't supported by this provider".to_owned()))
+    }
+
+    /// Up to `matches` stops within `radius` metres of a coordinate, nearest first. Their `lines` are left empty.
+    async fn nearby(&self, _latitude: f64, _longitude: f64, _radius: u32, _matches: u32) -> Result<Vec<Stop>> {
+        Err(Error::Config("Searching for nearby stops isn't supported by this provider".to_owned()))
+    }
+
+    /// Lines serving `stop`
+    async fn lines(&self, _stop: &str) -> Result<Vec<String>> {
+        Err(Error::Config("Looking up lines isn't supported by this provider".to_owned()))
+    }
+}
+
+/// Which departures a provider is asked for. Also the key they are cached under.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Board {
+    pub stop: String,
+    pub mode: BoardMode,
+
+    /// Show the board as of this time rather than now
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<DateTime<Utc>>,
+}
+
+/// Which journeys a provider is asked for. Also the key they are cached under.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Route {
+    pub origin: String,
+    pub destination: String,
+
+    /// How many connections to plan
+    pub connections: usize,
+}
+
+/// Which provider serves which of the configured stops. Stops without one of their own are served by the default EFA
+/// instance.
+#[derive(Clone)]
+pub struct Transit {
+    default: Arc<dyn TransitProvider>,
+    stops: HashMap<String, Arc<dyn TransitProvider>>,
+    providers: HashMap<String, Arc<dyn TransitProvider>>,
+    disruptions: Arc<Disruptions>,
+}
+
+impl Transit {
+    pub fn new(config: &Config, http: &HttpClient) -> Result<Self> {
+        let timezone: Tz = config.board.timezone.parse()
+            .map_err(|err| Error::Config(format!("Invalid timezone '{tz}': {err}", tz = config.board.timezone)))?;
+
+        let default: Arc<dyn TransitProvider> = Arc::new(EfaProvider::new(EFA_API, timezone, http.clone())?);
+
+        // Stops on the same EFA instance share a provider
+        let mut instances = HashMap::from([(EFA_API.to_owned(), default.clone())]);
+        let mut stops = HashMap::new();
+        let mut providers = HashMap::new();
+
+        for (name, provider) in &config.providers {
+            let provider = match instances.get(&provider.url) {
+                Some(provider) => provider.clone(),
+                None => {
+                    let instance: Arc<dyn TransitProvider> = Arc::new(EfaProvider::new(&provider.url, timezone, http.clone())?);
+                    instances.insert(provider.url.clone(), instance.clone());
+                    instance
+                }
+            };
+
+            providers.insert(name.clone(), provider);
+        }
+
+        for stop in &config.departure {
+            let provider: Arc<dyn TransitProvider> = match &stop.fixtures {
+                Some(fixtures) => Arc::new(FixtureProvider::new(fixtures)),
+                None => {
+                    let url = config.efa_url(stop)
+                        .map_err(|err| Error::Config(err.to_string()))?;
+
+                    match instances.get(url) {
+                        Some(provider) => provider.clone(),
+                        None => {
+                            let provider: Arc<dyn TransitProvider> = Arc::new(EfaProvider::new(url, timezone, http.clone())?);
+                            instances.insert(url.to_owned(), provider.clone());
+                            provider
+                        }
+                    }
+                }
+            };
+
+            stops.insert(stop.point.clone(), provider);
+        }
+
+        Ok(Self {
+            default,
+            stops,
+            providers,
+            disruptions: Arc::default(),
+        })
+    }
+
+    pub fn provider(&self, stop: &str) -> Arc<dyn TransitProvider> {
+        self.stops.get(stop)
+            .unwrap_or(&self.default)
+            .clone()
+    }
+
+    /// The provider configured as `name` in `[providers]`, or the default EFA instance
+    pub fn named(&self, name: Option<&str>) -> Option<Arc<dyn TransitProvider>> {
+        match name {
+            Some(name) => self.providers.get(name).cloned(),
+            None => Some(self.default.clone()),
+        }
+    }
+
+    /// Fetches `board` from the provider of its stop, taking note of any disruptions it mentions.
+    pub fn departures(&self, board: Board) -> impl Future<Output = Result<Vec<DepartureBoardStop>>> + Send + 'static {
+        let (provider, disruptions) = (self.provider(&board.stop), self.disruptions.clone());
+
+        async move {
+            let departures = provider.departures(&board).await?;
+            // Boards of the future may list planned works which aren't in effect yet
+            if board.at.is_none() {
+                disruptions.record(&format!("{stop}:{mode}", stop = board.stop, mode = board.mode.as_str()), &departures);
+            }
+
+            Ok(departures)
+        }
+    }
+
+    /// Plans journeys along `route` with the provider of its origin.
+    pub fn trips(&self, route: Route) -> impl Future<Output = Result<Vec<Journey>>> + Send + 'static {
+        let provider = self.provider(&route.origin);
+
+        async move { provider.trips(&route).await }
+    }
+
+    pub fn disruptions(&self) -> &Disruptions {
+        &self.disruptions
+    }
+}
@@ -0,0 +1,113 @@
+use crate::state::Caches;
+use crate::Error;
+use crate::Result;
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::web;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use common::config::Config;
+
+pub fn admin() -> actix_web::Scope {
+    web::scope("/admin")
+        .service(list)
+        .service(clear)
+        .service(invalidate)
+}
+
+/// Admin endpoints are only available with the bearer token from `[admin]`, and not at all if none is configured.
+fn authorised(req: &HttpRequest, cfg: &Config) -> bool {
+    let given = req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "));
+
+    cfg.admin.token.as_deref().is_some_and(|token| given == Some(token))
+}
+
+#[actix_web::get("/caches")]
+pub async fn list(req: HttpRequest, cfg: web::Data<Config>, caches: web::Data<Caches>) -> Result<impl Responder> {
+    if !authorised(&req, &cfg) {
+        return Err(Error::Unauthorized);
+    }
+
+    Ok(HttpResponse::Ok().json(caches.stats().await?))
+}
+
+#[actix_web::delete("/caches/{cache}")]
+pub async fn clear(req: HttpRequest, path: web::Path<String>, cfg: web::Data<Config>, caches: web::Data<Caches>) -> Result<impl Responder> {
+    if !authorised(&req, &cfg) {
+        return Err(Error::Unauthorized);
+    }
+
+    if caches.clear(&path).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(Error::NotFound(format!("There's no cache called '{name}'", name = path.as_str())))
+    }
+}
+
+#[actix_web::delete("/caches/{cache}/{key}")]
+pub async fn invalidate(req: HttpRequest, path: web::Path<(String, String)>, cfg: web::Data<Config>, caches: web::Data<Caches>) -> Result<impl Responder> {
+    if !authorised(&req, &cfg) {
+        return Err(Error::Unauthorized);
+    }
+
+    let (cache, key) = path.into_inner();
+
+    if caches.invalidate(&cache, &key).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(Error::NotFound(format!("'{key}' isn't cached in '{cache}'")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use actix_web::App;
+    use common::config::AdminConfig;
+
+    async fn status(token: Option<&str>, authorization: Option<&str>) -> StatusCode {
+        let config = Config {
+            admin: AdminConfig { token: token.map(str::to_owned) },
+            ..Default::default()
+        };
+
+        let caches = Caches::new(&config.cache).await.expect("Failed to set up caches");
+        let app = test::init_service(App::new()
+            .app_data(web::Data::new(config))
+            .app_data(web::Data::new(caches))
+            .service(admin())).await;
+
+        let req = authorization
+            .into_iter()
+            .fold(test::TestRequest::get().uri("/admin/caches"), |req, value| req.insert_header((AUTHORIZATION, value)))
+            .to_request();
+
+        test::call_service(&app, req).await.status()
+    }
+
+    #[actix_web::test]
+    async fn refuses_without_token() {
+        assert_eq!(status(Some("secret"), None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn refuses_wrong_token() {
+        assert_eq!(status(Some("secret"), Some("Bearer guess")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("secret"), Some("secret")).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn refuses_everything_unless_configured() {
+        assert_eq!(status(None, Some("Bearer ")).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn accepts_configured_token() {
+        assert_eq!(status(Some("secret"), Some("Bearer secret")).await, StatusCode::OK);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::state::Caches;
+use crate::transit::Transit;
+use crate::v1::buses;
+use crate::v1::buses::BoardQuery;
+use crate::v1::buses::Filter;
+use crate::v1::buses_schema::DepartureBoardStop;
+use crate::v1::conditional;
+use crate::Result;
+use actix_web::web;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use chrono::DateTime;
+use chrono::FixedOffset;
+use common::config::Config;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GroupQuery {
+    /// Group departures by line and direction instead of listing them in order
+    grouped: bool,
+
+    /// How many departures to list per group
+    per_group: usize,
+}
+
+impl Default for GroupQuery {
+    fn default() -> Self {
+        Self {
+            grouped: false,
+            per_group: 2,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct Group {
+    pub line: String,
+    pub direction: String,
+    pub departures: Vec<DepartureBoardStop>,
+}
+
+/// Every configured stop as one board. Filters apply as on `/v1/buses`, except that `limit` counts departures, or
+/// groups, across all stops.
+#[actix_web::get("/board")]
+pub async fn board(req: HttpRequest, query: web::Query<BoardQuery>, filter: web::Query<Filter>, group: web::Query<GroupQuery>, cfg: web::Data<Config>, caches: web::Data<Caches>, transit: web::Data<Transit>) -> Result<HttpResponse> {
+    if query.too_far_ahead(&cfg.board) {
+        return Err(buses::too_far_ahead(&cfg.board));
+    }
+
+    let stops = buses::all_stops(&cfg, &caches, &transit, &query).await?;
+    let (fetched, stale, errors) = (stops.fetched(), stops.stale(), serde_json::to_value(stops.failures())?);
+
+    let departures = merge(stops.values.into_iter().flat_map(|(_, list)| list.value))
+        .into_iter()
+        .filter(|departure| filter.matches(departure, query.now()));
+
+    let body = if group.grouped {
+        serde_json::json! {{
+            "time": fetched,
+            "stale": stale,
+            "errors": errors,
+            "groups": self::group(departures, group.per_group)
+                .into_iter()
+                .take(filter.limit())
+                .collect::<Vec<_>>(),
+        }}
+    } else {
+        serde_json::json! {{
+            "time": fetched,
+            "stale": stale,
+            "errors": errors,
+            "departures": departures.take(filter.limit()).collect::<Vec<_>>(),
+        }}
+    };
+
+    // Annotated per request like `/v1/buses`, so only the ETag can tell whether the board is current
+    Ok(conditional::respond(&req, None, body))
+}
+
+/// Identifies a trip across stops. Neighbouring platforms report the same trip at the same scheduled time.
+type Trip = (String, String, DateTime<FixedOffset>);
+
+/// Combines the departures of several stops, listing each trip only once, in the order they're expected to leave.
+/// Where a trip is reported more than once, realtime data wins over the timetable.
+fn merge(departures: impl IntoIterator<Item = DepartureBoardStop>) -> Vec<DepartureBoardStop> {
+    let mut trips: HashMap<Trip, DepartureBoardStop> = HashMap::new();
+
+    for departure in departures {
+        let trip = (departure.line.clone(), departure.direction.clone(), departure.given_arrival);
+
+        match trips.get(&trip) {
+            Some(existing) if existing.is_realtime || !departure.is_realtime => {}
+            _ => {
+                trips.insert(trip, departure);
+            }
+        }
+    }
+
+    let mut departures = trips.into_values().collect::<Vec<_>>();
+    departures.sort_by(|a, b| a.expected_arrival.cmp(&b.expected_arrival)
+        .then_with(|| a.line.cmp(&b.line)));
+
+    departures
+}
+
+/// Groups departures, which should already be in order, by line and direction. Groups are ordered by their next
+/// departure.
+fn group(departures: impl IntoIterator<Item = DepartureBoardStop>, per_group: usize) -> Vec<Group> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut index = HashMap::new();
+
+    for departure in departures {
+        let i = *index.entry((departure.line.clone(), departure.direction.clone()))
+            .or_insert_with(|| {
+                groups.push(Group {
+                    line: departure.line.clone(),
+                    direction: departure.direction.clone(),
+                    departures: Vec::new(),
+                });
+
+                groups.len() - 1
+            });
+
+        if groups[i].departures.len() < per_group {
+            groups[i].departures.push(departure);
+        }
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn departure(line: &str, direction: &str, given: &str, expected: &str) -> DepartureBoardStop {
+        DepartureBoardStop::example(line, direction, &format!("2030-01-02T{given}:00+01:00"), &format!("2030-01-02T{expected}:00+01:00"))
+    }
+
+    fn realtime(mut departure: DepartureBoardStop, stop: &str) -> DepartureBoardStop {
+        departure.is_realtime = true;
+        departure.stop = stop.to_owned();
+        departure
+    }
+
+    #[test]
+    fn same_trip_is_listed_once_preferring_realtime() {
+        for reversed in [false, true] {
+            let mut departures = vec![
+                departure("5", "WHO", "14:00", "14:00"),
+                realtime(departure("5", "WHO", "14:00", "14:03"), "Platform B"),
+            ];
+
+            if reversed {
+                departures.reverse();
+            }
+
+            let merged = merge(departures);
+
+            assert_eq!(merged.len(), 1);
+            assert!(merged[0].is_realtime);
+            assert_eq!(merged[0].stop, "Platform B");
+            assert_eq!(merged[0].expected_arrival.format("%H:%M").to_string(), "14:03");
+        }
+    }
+
+    #[test]
+    fn merged_in_expected_order() {
+        let merged = merge([
+            departure("7", "Sand", "14:10", "14:10"),
+            departure("5", "WHO", "14:00", "14:15"),
+            departure("3", "Waldhäuser Ost", "14:05", "14:05"),
+        ]);
+
+        let lines = merged.iter().map(|departure| departure.line.as_str()).collect::<Vec<_>>();
+        assert_eq!(lines, ["3", "7", "5"]);
+    }
+
+    #[test]
+    fn groups_by_next_departure() {
+        let groups = group(merge([
+            departure("7", "Sand", "14:02", "14:02"),
+            departure("5", "WHO", "14:01", "14:01"),
+            departure("5", "WHO", "14:11", "14:11"),
+            departure("5", "WHO", "14:21", "14:21"),
+            departure("7", "Sand", "14:12", "14:12"),
+            departure("5", "Hbf", "14:03", "14:03"),
+        ]), 2);
+
+        let summary = groups.iter()
+            .map(|group| (group.line.as_str(), group.direction.as_str(), group.departures.len()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(summary, [("5", "WHO", 2), ("7", "Sand", 2), ("5", "Hbf", 1)]);
+        assert_eq!(groups[0].departures[1].expected_arrival.format("%H:%M").to_string(), "14:11");
+    }
+}
@@ -1,176 +1,383 @@
-use crate::v1::buses_schema::BusSchema;
 use crate::v1::buses_schema::DepartureBoardStop;
-use crate::v1::buses_schema::RealDateTimeClass;
+use crate::v1::buses_schema::Reach;
+use crate::Error;
 use crate::Result;
+use actix_web::web;
+use actix_web::HttpRequest;
 use actix_web::HttpResponse;
-use actix_web::Responder;
-use chrono::Local;
-use chrono::TimeZone;
+use chrono::DateTime;
+use chrono::FixedOffset;
+use chrono::Utc;
+use common::config::BoardConfig;
+use common::config::BoardMode;
 use common::config::Config;
+use common::config::DepartureConfig;
 use common::prelude::tokio::task::JoinSet;
-use common::prelude::DepartureConfig;
-use reqwest::Url;
 use serde::Deserialize;
-use serde::Serialize;
+use serde::Deserializer;
 use std::collections::HashMap;
-use std::io::Error;
-use std::time::{Duration, SystemTime};
-use crate::cache::Cache;
+use crate::state::Caches;
+use crate::transit::Board;
+use crate::transit::Transit;
+use crate::v1::conditional;
+use crate::v1::partial::Partial;
+
+/// Which board to show for each stop
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BoardQuery {
+    /// `dep` or `arr`. Defaults to the stop's `mode` from the config.
+    mode: Option<BoardMode>,
+
+    /// RFC 3339 time to show the board for instead of now
+    #[serde(deserialize_with = "rfc3339")]
+    at: Option<DateTime<FixedOffset>>,
+}
 
-const BUS_API: &'static str = "https://www.efa-bw.de/mobidata-bw/XML_DM_REQUEST";
+/// Unencoded `+` in query strings turn into spaces, which would otherwise mangle offsets like `+02:00`
+fn rfc3339<'de, D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Option<DateTime<FixedOffset>>, D::Error> {
+    let at = String::deserialize(deserializer)?;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Query {
-    #[serde(rename = "outputFormat")]
-    output_format: &'static str,
+    DateTime::parse_from_rfc3339(&at.replace(' ', "+"))
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
 
-    mode: &'static str,
+/// Narrows down the departures of each stop. Lists are comma-separated, e.g. `?lines=5,7&limit=4`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Filter {
+    /// How many departures to return per stop at most
+    limit: Option<usize>,
 
-    #[serde(rename = "useProxFootSearch")]
-    include_proximate: u8,
+    /// Only departures of these lines
+    #[serde(deserialize_with = "comma_separated")]
+    lines: Vec<String>,
 
-    #[serde(rename = "name_dm")]
-    stop_id: String,
+    /// Only departures whose direction contains one of these, ignoring case
+    #[serde(deserialize_with = "comma_separated")]
+    directions: Vec<String>,
 
-    // #[serde(rename = "itdDate")]
-    // date: String,
-    //
-    // #[serde(rename = "itdTime")]
-    // time: String,
-    limit: usize,
+    /// Only departures leaving within this many minutes
+    max_minutes: Option<i64>,
 
-    #[serde(rename = "itdDateTimeDepArr")]
-    depart_or_arrive: &'static str,
+    /// No departures from these platforms
+    #[serde(deserialize_with = "comma_separated")]
+    exclude_platforms: Vec<String>,
 
-    type_dm: &'static str,
+    /// No departures which can't be reached in time anymore
+    hide_missed: bool,
 }
 
-impl Default for Query {
-    fn default() -> Self {
-        Self {
-            output_format: "JSON",
-            mode: "direct",
-            include_proximate: 1,
-            stop_id: "".to_string(),
-            limit: 50,
-            depart_or_arrive: "dep",
-            type_dm: "any",
+fn comma_separated<'de, D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Vec<String>, D::Error> {
+    Ok(String::deserialize(deserializer)?
+        .split(',')
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .map(str::to_owned)
+        .collect())
+}
+
+impl BoardQuery {
+    /// Whether `at` lies further ahead than boards may be requested
+    pub(crate) fn too_far_ahead(&self, config: &BoardConfig) -> bool {
+        let max = chrono::Duration::from_std(config.max_look_ahead()).unwrap_or(chrono::Duration::MAX);
+        self.at.is_some_and(|at| at.with_timezone(&Utc) - Utc::now() > max)
+    }
+
+    /// The time the board is shown for, which `maxMinutes` counts from
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        self.at.map(|at| at.with_timezone(&Utc)).unwrap_or_else(Utc::now)
+    }
+
+    /// Drops the departures before `at`. Boards are fetched from the start of `at`'s time bucket, so they may begin a
+    /// few minutes early.
+    pub(crate) fn trim(&self, departures: &mut Vec<DepartureBoardStop>) {
+        if let Some(at) = self.at {
+            departures.retain(|departure| departure.expected_arrival >= at);
+        }
+    }
+
+    fn board(&self, stop: &DepartureConfig, config: &BoardConfig) -> Board {
+        let bucket = config.time_bucket().as_secs().max(1) as i64;
+
+        Board {
+            stop: stop.point.clone(),
+            mode: self.mode.or(stop.mode).unwrap_or_default(),
+            at: self.at
+                .map(|at| at.timestamp())
+                .and_then(|at| DateTime::from_timestamp(at - at.rem_euclid(bucket), 0)),
         }
     }
 }
 
-impl Query {
-    fn with_limit(mut self, limit: usize) -> Self {
-        self.limit = limit;
-        return self;
+impl Filter {
+    /// `now` is the time the board is shown for
+    pub(crate) fn matches(&self, departure: &DepartureBoardStop, now: DateTime<Utc>) -> bool {
+        let line = self.lines.is_empty() || self.lines.contains(&departure.line);
+
+        let direction = self.directions.is_empty() || self.directions.iter()
+            .any(|i| departure.direction.to_lowercase().contains(&i.to_lowercase()));
+
+        let soon = self.max_minutes
+            .is_none_or(|max| departure.expected_arrival.signed_duration_since(now).num_minutes() <= max);
+
+        let platform = departure.platform.as_ref()
+            .is_none_or(|platform| !self.exclude_platforms.contains(&platform.code));
+
+        let reachable = !self.hide_missed || departure.reach != Some(Reach::Missed);
+
+        line && direction && soon && platform && reachable
     }
 
-    fn with_stop(mut self, stop: impl AsRef<str>) -> Self {
-        self.stop_id = stop.as_ref().to_string();
-        return self;
+    pub(crate) fn limit(&self) -> usize {
+        self.limit.unwrap_or(usize::MAX)
+    }
+
+    pub fn apply(&self, departures: Vec<DepartureBoardStop>, now: DateTime<Utc>) -> Vec<DepartureBoardStop> {
+        departures.into_iter()
+            .filter(|departure| self.matches(departure, now))
+            .take(self.limit())
+            .collect()
     }
 }
 
-impl From<&Vec<DepartureConfig>> for Query {
-    fn from(value: &Vec<DepartureConfig>) -> Self {
-        Self {
-            stop_id: value.iter().map(|i| &i.point).cloned().collect(),
-            include_proximate: 0,
-            ..Default::default()
-        }
+#[actix_web::get("/buses")]
+pub async fn buses(req: HttpRequest, query: web::Query<BoardQuery>, filter: web::Query<Filter>, cfg: web::Data<Config>, caches: web::Data<Caches>, transit: web::Data<Transit>) -> Result<HttpResponse> {
+    if query.too_far_ahead(&cfg.board) {
+        return Err(too_far_ahead(&cfg.board));
     }
+
+    let stops = all_stops(&cfg, &caches, &transit, &query).await?;
+    let fetched = stops.fetched();
+
+    // `reach` and `leaveInMinutes` change with every request, so there's no telling when the body last changed
+    Ok(conditional::respond(&req, None, serde_json::json! {{
+        "time": fetched,
+        "stale": stops.stale(),
+        "errors": stops.failures(),
+        "stops": stops.values.into_iter()
+            .map(|(stop, list)| (stop, filter.apply(list.value, query.now())))
+            .collect::<HashMap<_, _>>()
+    }}))
 }
 
-static DEPARTURES: Cache<String, Vec<DepartureBoardStop>> = Cache::new(Duration::from_secs(10));
+/// Departures of a single configured stop
+#[actix_web::get("/buses/{stop}")]
+pub async fn single_stop(req: HttpRequest, path: web::Path<String>, query: web::Query<BoardQuery>, filter: web::Query<Filter>, cfg: web::Data<Config>, caches: web::Data<Caches>, transit: web::Data<Transit>) -> Result<HttpResponse> {
+    let Some(stop) = cfg.departure.iter().find(|i| i.point == *path) else {
+        return Err(Error::NotFound(format!("Stop '{path}' isn't configured", path = path.as_str())));
+    };
 
-#[actix_web::get("/buses")]
-pub async fn buses(cfg: actix_web::web::Data<Config>) -> Result<impl Responder> {
+    if query.too_far_ahead(&cfg.board) {
+        return Err(too_far_ahead(&cfg.board));
+    }
+
+    let board = query.board(stop, &cfg.board);
+    let mut list = caches.departures.get(board.clone(), || transit.departures(board.clone())).await?;
+    query.trim(&mut list.value);
+    annotate(&mut list.value, stop, &cfg.board);
+
+    Ok(conditional::respond(&req, None, serde_json::json! {{
+        "time": list.fetched,
+        "stale": list.stale,
+        "stop": board.stop,
+        "mode": board.mode,
+        "at": board.at,
+        "departures": filter.apply(list.value, query.now()),
+    }}))
+}
+
+pub(crate) fn too_far_ahead(config: &BoardConfig) -> Error {
+    Error::BadRequest(format!("`at` may be at most {hours} hours ahead", hours = config.max_look_ahead().as_secs() / 3600))
+}
+
+/// Boards of every configured stop, fetched concurrently. Stops whose board can't be fetched are listed as errors.
+pub(crate) async fn all_stops(cfg: &Config, caches: &Caches, transit: &Transit, query: &BoardQuery) -> Result<Partial<String, Vec<DepartureBoardStop>>> {
     let mut tasks = JoinSet::new();
 
-    for stop in cfg.departure.iter().map(|i| i.point.clone()) {
+    for stop in cfg.departure.iter().cloned() {
+        let board = query.board(&stop, &cfg.board);
+        let (caches, transit, config, query) = (caches.clone(), transit.clone(), cfg.board.clone(), query.clone());
 
         tasks.spawn(async move {
-            (stop.clone(), DEPARTURES.get(stop.clone(), async || {
-                get_times(stop.clone()).await
-            }).await)
+            let list = caches.departures.get(board.clone(), || transit.departures(board)).await
+                .map(|mut list| {
+                    query.trim(&mut list.value);
+                    annotate(&mut list.value, &stop, &config);
+                    list
+                });
+
+            (stop.point, list)
         });
     }
 
-    let stops = tasks.join_all().await
-        .into_iter()
-        .map(|(stop, list)| list.map(|i| (stop, i)))
-        .collect::<Result<HashMap<String, Vec<DepartureBoardStop>>>>()?;
+    Partial::collect(tasks.join_all().await)
+}
 
-    Ok(HttpResponse::Ok().json(serde_json::json! {{
-        "time": SystemTime::now(),
-        "stops": stops
-    }}))
+fn annotate(departures: &mut [DepartureBoardStop], stop: &DepartureConfig, config: &BoardConfig) {
+    for departure in departures {
+        departure.annotate(stop.walking_time(), config.hurry());
+    }
 }
 
-fn parse_date_time(date: RealDateTimeClass) -> Result<chrono::DateTime<Local>> {
-    let (yyyy, MM, dd, hh, mm) = (
-        date.year.parse::<i32>().map_err(Error::other)?,
-        date.month.parse::<u32>().map_err(Error::other)?,
-        date.day.parse::<u32>().map_err(Error::other)?,
-        date.hour.parse::<u32>().map_err(Error::other)?,
-        date.minute.parse::<u32>().map_err(Error::other)?,
-    );
+/// Fetches `board` into the cache, regardless of whether the cached one has expired yet.
+pub(crate) async fn refresh(caches: &Caches, transit: &Transit, board: Board) -> Result<()> {
+    caches.departures.refresh(board.clone(), || transit.departures(board)).await
+        .map(|_| ())
+}
 
-    let date = chrono::NaiveDate::from_ymd_opt(yyyy, MM, dd);
-    let time = chrono::NaiveTime::from_hms_opt(hh, mm, 0);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpClient;
+    use crate::v1::buses_schema::DepartureStatus;
+    use crate::v1::buses_schema::Platform;
+    use crate::v1::buses_schema::TransportMode;
 
-    let datetime = date
-        .and_then(|date| Some(date.and_time(time?)))
-        .ok_or(Error::other("No time provided"))?;
+    fn directions(departures: &[DepartureBoardStop]) -> Vec<&str> {
+        departures.iter().map(|departure| departure.direction.as_str()).collect()
+    }
 
-    // log::trace!("Assuming Timezone: {tz}", tz=Local::now().offset());
+    #[test]
+    fn max_minutes_counts_from_at() {
+        let query = BoardQuery {
+            at: Some(DateTime::parse_from_rfc3339("2030-01-02T14:00:00+01:00").unwrap()),
+            ..Default::default()
+        };
 
-    Ok(Local.from_local_datetime(&datetime).unwrap())
-}
+        let filter = Filter { max_minutes: Some(30), ..Default::default() };
+
+        let departures = vec![
+            DepartureBoardStop::example("5", "Soon", "2030-01-02T14:20:00+01:00", "2030-01-02T14:25:00+01:00"),
+            DepartureBoardStop::example("5", "Later", "2030-01-02T14:40:00+01:00", "2030-01-02T14:40:00+01:00"),
+        ];
+
+        assert_eq!(directions(&filter.apply(departures, query.now())), ["Soon"]);
+    }
+
+    #[test]
+    fn departures_before_at_are_dropped() {
+        let query = BoardQuery {
+            at: Some(DateTime::parse_from_rfc3339("2030-01-02T14:04:00+01:00").unwrap()),
+            ..Default::default()
+        };
 
-async fn get_times(stop: String) -> Result<Vec<DepartureBoardStop>> {
-    let mut uri = Url::parse(BUS_API).map_err(Error::other)?;
-    let client = reqwest::ClientBuilder::new()
-        .build()
-        .map_err(Error::other)?;
-
-    let query = serde_qs::to_string(&Query {
-        stop_id: stop.to_string(),
-        ..Default::default()
-    })
-    .map_err(Error::other)?;
-
-    uri.set_query(Some(&query));
-
-    log::debug!("Bus URL: {uri:?}", uri = uri.to_string());
-
-    let req = client.get(uri).send().await.expect("Failed to get buses");
-
-    let res: BusSchema = req.json().await.expect("Failed to get buses");
-
-    res.departure_list
-        .iter()
-        .map(|line| {
-            let eta = parse_date_time(
-                line.real_date_time
-                    .clone()
-                    .unwrap_or(line.date_time.clone()),
-            )
-            .map_err(Error::other)?;
-
-            let given_eta = parse_date_time(
-                line.date_time
-                    .clone(),
-            )
-            .map_err(Error::other)?;
-
-            Ok(DepartureBoardStop {
-                stop: line.stop_name.clone(),
-                line: line.serving_line.symbol.to_string(),
-                direction: line.serving_line.direction.to_string(),
-                expected_arrival: eta,
-                given_arrival: given_eta
-            })
-        })
-        .collect()
+        let config = BoardConfig { time_bucket: 300_000, ..Default::default() };
+        let board = query.board(&DepartureConfig::default(), &config);
+        assert_eq!(board.at, Some(DateTime::parse_from_rfc3339("2030-01-02T14:00:00+01:00").unwrap().to_utc()));
+
+        let mut departures = vec![
+            DepartureBoardStop::example("5", "Before", "2030-01-02T14:01:00+01:00", "2030-01-02T14:01:00+01:00"),
+            DepartureBoardStop::example("5", "Delayed", "2030-01-02T14:02:00+01:00", "2030-01-02T14:04:00+01:00"),
+            DepartureBoardStop::example("5", "After", "2030-01-02T13:05:00Z", "2030-01-02T13:05:00Z"),
+        ];
+
+        query.trim(&mut departures);
+        assert_eq!(directions(&departures), ["Delayed", "After"]);
+    }
+
+    #[test]
+    fn max_minutes_counts_from_now() {
+        let filter = Filter { max_minutes: Some(30), ..Default::default() };
+        let at = |minutes| (Utc::now() + chrono::Duration::minutes(minutes)).to_rfc3339();
+
+        let departures = vec![
+            DepartureBoardStop::example("5", "Soon", &at(10), &at(10)),
+            DepartureBoardStop::example("5", "Later", &at(60), &at(60)),
+        ];
+
+        assert_eq!(directions(&filter.apply(departures, BoardQuery::default().now())), ["Soon"]);
+    }
+
+    fn departures() -> Vec<DepartureBoardStop> {
+        let at = |minutes| (Utc::now() + chrono::Duration::minutes(minutes)).to_rfc3339();
+        let on = |platform: &str, mut departure: DepartureBoardStop| {
+            departure.platform = Some(Platform { code: platform.to_owned(), name: format!("Bussteig {platform}") });
+            departure
+        };
+
+        vec![
+            on("A", DepartureBoardStop::example("5", "Tübingen WHO", &at(5), &at(5))),
+            on("B", DepartureBoardStop::example("7", "Tübingen Hbf", &at(10), &at(10))),
+            on("A", DepartureBoardStop::example("5", "Lustnau", &at(15), &at(15))),
+            DepartureBoardStop::example("RB 63", "Horb", &at(20), &at(20)),
+        ]
+    }
+
+    fn filter(query: &str) -> Filter {
+        web::Query::<Filter>::from_query(query).expect("Invalid filter").into_inner()
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(directions(&filter("lines=5, RB 63").apply(departures(), Utc::now())), ["Tübingen WHO", "Lustnau", "Horb"]);
+    }
+
+    #[test]
+    fn directions_ignore_case_and_match_substrings() {
+        assert_eq!(directions(&filter("directions=tübingen").apply(departures(), Utc::now())), ["Tübingen WHO", "Tübingen Hbf"]);
+        assert_eq!(directions(&filter("directions=HBF,lust").apply(departures(), Utc::now())), ["Tübingen Hbf", "Lustnau"]);
+    }
+
+    #[test]
+    fn exclude_platforms() {
+        assert_eq!(directions(&filter("excludePlatforms=A").apply(departures(), Utc::now())), ["Tübingen Hbf", "Horb"]);
+    }
+
+    #[test]
+    fn limit_applies_after_filtering() {
+        assert_eq!(directions(&filter("lines=5&limit=1").apply(departures(), Utc::now())), ["Tübingen WHO"]);
+        assert_eq!(directions(&filter("limit=0").apply(departures(), Utc::now())), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn without_filters() {
+        assert_eq!(filter("").apply(departures(), Utc::now()).len(), 4);
+    }
+
+    /// Goes through `Transit` and the cache the same way the handlers do, with one stop served from
+    /// `tests/fixtures/api` and one whose fixture is missing
+    #[actix_web::test]
+    async fn all_stops_from_fixtures() {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/api");
+        let stop = |point: &str| DepartureConfig {
+            point: point.to_owned(),
+            walking_time: Some(240_000),
+            fixtures: Some(fixtures.into()),
+            ..Default::default()
+        };
+
+        let config = Config {
+            departure: vec![stop("hbf"), stop("missing")],
+            ..Default::default()
+        };
+
+        let caches = Caches::new(&config.cache).await.expect("Failed to set up caches");
+        let http = HttpClient::new(&config.http).expect("Failed to set up HTTP client");
+        let transit = Transit::new(&config, &http).expect("Failed to set up providers");
+
+        let stops = all_stops(&config, &caches, &transit, &BoardQuery::default()).await.expect("No stop succeeded");
+
+        assert_eq!(stops.values.len(), 1);
+        let (point, board) = &stops.values[0];
+        assert_eq!(point, "hbf");
+        assert!(!board.stale);
+
+        let bus = &board.value[0];
+        assert_eq!((bus.line.as_str(), bus.direction.as_str()), ("5", "Tübingen WHO"));
+        assert_eq!(bus.platform.as_ref().map(|platform| platform.code.as_str()), Some("A"));
+        assert_eq!(bus.mode, TransportMode::CityBus);
+        assert_eq!(bus.delay_minutes, Some(2));
+        assert!(bus.is_realtime);
+        assert!(bus.reach.is_some() && bus.leave_in_minutes.is_some(), "Departures weren't annotated");
+
+        let train = &board.value[1];
+        assert_eq!(train.line, "RB 63");
+        assert_eq!(train.status, DepartureStatus::NoRealtime);
+        assert_eq!(train.hints.len(), 1);
+
+        assert_eq!(stops.errors.len(), 1);
+        assert_eq!(stops.errors[0].0, "missing");
+    }
 }
@@ -1,4 +1,8 @@
-use chrono::{Local, Utc};
+use crate::hash::fnv1a;
+use chrono::FixedOffset;
+use chrono::TimeDelta;
+use chrono::Utc;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -12,6 +16,9 @@ pub struct BusSchema {
     #[serde(rename = "option")]
     json_schema_option: JsonSchemaOption,
     serving_lines: ServingLines,
+
+    /// Called `arrivalList` when asking for arrivals, but otherwise the same
+    #[serde(alias = "arrivalList")]
     pub(crate) departure_list: Vec<DepartureList>,
 }
 
@@ -50,17 +57,17 @@ pub struct DepartureList {
     y: String,
     map_name: String,
     area: String,
-    platform: String,
-    platform_name: String,
+    pub(crate) platform: String,
+    pub(crate) platform_name: String,
     pub stop_name: String,
     #[serde(rename = "nameWO")]
     name_wo: String,
     countdown: String,
     pub(crate) date_time: RealDateTimeClass,
     pub(crate) real_date_time: Option<RealDateTimeClass>,
-    realtime_trip_status: Option<String>,
+    pub(crate) realtime_trip_status: Option<String>,
     pub(crate) serving_line: ServingLine,
-    operator: Operator,
+    pub(crate) operator: Operator,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -77,7 +84,7 @@ pub struct RealDateTimeClass {
 #[serde(rename_all = "camelCase")]
 pub struct Operator {
     code: String,
-    name: String,
+    pub(crate) name: String,
     public_code: String,
 }
 
@@ -88,15 +95,15 @@ pub struct ServingLine {
     code: String,
     number: String,
     pub(crate) symbol: String,
-    mot_type: String,
+    pub(crate) mot_type: String,
     mt_subcode: String,
-    realtime: String,
+    pub(crate) realtime: String,
     pub(crate) direction: String,
     direction_from: String,
-    train_num: String,
+    pub(crate) train_num: String,
     name: String,
-    delay: Option<String>,
-    hints: Option<Vec<Hint>>,
+    pub(crate) delay: Option<String>,
+    pub(crate) hints: Option<Vec<Hint>>,
     li_erg_ri_proj: LiErgRiProj,
 
     #[serde(rename = "destID")]
@@ -107,7 +114,7 @@ pub struct ServingLine {
 
 #[derive(Serialize, Deserialize)]
 pub struct Hint {
-    content: String,
+    pub(crate) content: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -302,9 +309,203 @@ pub struct DepartureBoardStop {
     pub line: String,
     pub direction: String,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+
+    #[serde(default)]
+    pub mode: TransportMode,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+
+    #[serde(default, rename = "trainNumber", skip_serializing_if = "Option::is_none")]
+    pub train_number: Option<String>,
+
     #[serde(rename = "expectedArrival")]
-    pub expected_arrival: chrono::DateTime<Local>,
+    pub expected_arrival: chrono::DateTime<FixedOffset>,
 
     #[serde(rename = "givenArrival")]
-    pub given_arrival: chrono::DateTime<Local>
-}
\ No newline at end of file
+    pub given_arrival: chrono::DateTime<FixedOffset>,
+
+    /// How late the departure is according to realtime data. Absent without realtime data or if it was cancelled.
+    #[serde(default, rename = "delayMinutes")]
+    pub delay_minutes: Option<i64>,
+
+    #[serde(default, rename = "isRealtime")]
+    pub is_realtime: bool,
+
+    #[serde(default)]
+    pub status: DepartureStatus,
+
+    /// Whether the departure can still be reached from the dashboard, given the stop's walking time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reach: Option<Reach>,
+
+    /// How long until one has to set off to catch this departure. Negative if it's too late already.
+    #[serde(default, rename = "leaveInMinutes", skip_serializing_if = "Option::is_none")]
+    pub leave_in_minutes: Option<i64>,
+
+    /// Disruption notices which apply to this departure, as listed by `/v1/disruptions`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<DepartureHint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Reach {
+    Catchable,
+    Hurry,
+    Missed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DepartureHint {
+    /// Derived from the content, so the same notice has the same id wherever it appears
+    pub id: String,
+    pub content: String,
+}
+
+impl DepartureBoardStop {
+    /// Works out whether the departure can be reached when it takes `walking_time` to get to the stop. Computed
+    /// relative to now whenever the departure is handed out, rather than when it was fetched.
+    pub fn annotate(&mut self, walking_time: Duration, hurry: Duration) {
+        let leave_in = self.expected_arrival.with_timezone(&Utc) - Utc::now() - TimeDelta::from_std(walking_time).unwrap_or_default();
+
+        self.leave_in_minutes = Some(leave_in.num_seconds().div_euclid(60));
+        self.reach = Some(match leave_in.to_std() {
+            Err(_) => Reach::Missed,
+            Ok(leave_in) if leave_in < hurry => Reach::Hurry,
+            Ok(_) => Reach::Catchable,
+        });
+    }
+}
+
+impl DepartureHint {
+    pub fn new(content: impl Into<String>) -> Self {
+        let content = content.into();
+
+        Self {
+            id: format!("{hash:016x}", hash = fnv1a(content.trim().as_bytes())),
+            content,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DepartureStatus {
+    OnTime,
+    Delayed,
+    Cancelled,
+
+    /// Runs in addition to the timetable
+    ExtraTrip,
+
+    /// Only the timetable is known
+    #[default]
+    NoRealtime,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Platform {
+    /// Bay letter or track number
+    pub code: String,
+
+    /// As signposted at the stop, e.g. `Bussteig A`
+    pub name: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransportMode {
+    Train,
+    SuburbanRailway,
+    Underground,
+    LightRail,
+    Tram,
+    CityBus,
+    RegionalBus,
+    ExpressBus,
+    CableCar,
+    Ferry,
+    OnDemand,
+    Plane,
+    RegionalTrain,
+    NationalTrain,
+    InternationalTrain,
+    HighSpeedTrain,
+    RailReplacementBus,
+    ShuttleTrain,
+    CommunityBus,
+
+    /// Footpaths between the legs of a journey
+    Walk,
+
+    #[default]
+    Other,
+}
+
+#[cfg(test)]
+impl DepartureBoardStop {
+    /// A departure without realtime data, at times given in RFC 3339
+    pub(crate) fn example(line: &str, direction: &str, given: &str, expected: &str) -> Self {
+        Self {
+            stop: "Hbf".to_owned(),
+            line: line.to_owned(),
+            direction: direction.to_owned(),
+            platform: None,
+            mode: TransportMode::default(),
+            operator: None,
+            train_number: None,
+            expected_arrival: chrono::DateTime::parse_from_rfc3339(expected).unwrap(),
+            given_arrival: chrono::DateTime::parse_from_rfc3339(given).unwrap(),
+            delay_minutes: None,
+            is_realtime: false,
+            status: DepartureStatus::default(),
+            reach: None,
+            leave_in_minutes: None,
+            hints: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Annotated with 4 minutes' walk and 2 minutes to spare before one has to hurry
+    fn annotated(minutes: i64) -> (Option<Reach>, Option<i64>) {
+        let at = (Utc::now() + TimeDelta::minutes(minutes)).to_rfc3339();
+
+        let mut departure = DepartureBoardStop::example("5", "Hbf", &at, &at);
+        departure.annotate(Duration::from_secs(240), Duration::from_secs(120));
+
+        (departure.reach, departure.leave_in_minutes)
+    }
+
+    #[test]
+    fn missed_once_walking_takes_too_long() {
+        assert_eq!(annotated(3), (Some(Reach::Missed), Some(-1)));
+    }
+
+    #[test]
+    fn hurry_within_hurry_of_setting_off() {
+        // Just under a minute left, which is floored to 0
+        assert_eq!(annotated(5), (Some(Reach::Hurry), Some(0)));
+    }
+
+    #[test]
+    fn catchable_with_time_to_spare() {
+        assert_eq!(annotated(9), (Some(Reach::Catchable), Some(4)));
+    }
+
+    #[test]
+    fn realtime_delays_count() {
+        let given = Utc::now().to_rfc3339();
+        let expected = (Utc::now() + TimeDelta::minutes(9)).to_rfc3339();
+
+        let mut departure = DepartureBoardStop::example("5", "Hbf", &given, &expected);
+        departure.annotate(Duration::from_secs(240), Duration::from_secs(120));
+
+        assert_eq!(departure.reach, Some(Reach::Catchable));
+    }
+}
@@ -0,0 +1,155 @@
+use actix_web::http::header::CacheControl;
+use actix_web::http::header::CacheDirective;
+use actix_web::http::header::ETag;
+use actix_web::http::header::EntityTag;
+use actix_web::http::header::Header;
+use actix_web::http::header::HttpDate;
+use actix_web::http::header::IF_NONE_MATCH;
+use actix_web::http::header::IfModifiedSince;
+use actix_web::http::header::IfNoneMatch;
+use actix_web::http::header::LastModified;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use std::time::Duration;
+use std::time::SystemTime;
+use crate::hash::fnv1a;
+
+/// Responds with `body`, or with `304 Not Modified` if the validators the client sent show that its copy is still
+/// current. `last_modified` is when the body last changed, see [`last_modified`]. Without it only the ETag is sent and
+/// `If-Modified-Since` is ignored.
+pub fn respond(req: &HttpRequest, last_modified: Option<SystemTime>, body: serde_json::Value) -> HttpResponse {
+    let body = body.to_string();
+
+    let etag = EntityTag::new_strong(format!("{hash:016x}", hash = fnv1a(body.as_bytes())));
+
+    // HTTP dates only have second precision, so compare against what the client actually got to see
+    let last_modified = last_modified.map(|last_modified| {
+        let seconds = last_modified.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs();
+        HttpDate::from(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+    });
+
+    // If-None-Match takes precedence over If-Modified-Since whenever both are sent (RFC 9110 §13.2.2)
+    let not_modified = if req.headers().contains_key(IF_NONE_MATCH) {
+        match IfNoneMatch::parse(req) {
+            Ok(IfNoneMatch::Any) => true,
+            Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+            Err(_) => false,
+        }
+    } else {
+        last_modified.is_some_and(|last_modified| IfModifiedSince::parse(req)
+            .is_ok_and(|IfModifiedSince(since)| SystemTime::from(last_modified) <= SystemTime::from(since)))
+    };
+
+    let mut res = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+
+    res.insert_header(ETag(etag))
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]));
+
+    if let Some(last_modified) = last_modified {
+        res.insert_header(LastModified(last_modified));
+    }
+
+    if not_modified {
+        res.finish()
+    } else {
+        res.content_type("application/json").body(body)
+    }
+}
+
+/// The time a body made up of values fetched at `fetched` last changed, if that's known. A stale or partial body can
+/// change without anything being fetched again, when the `stale` flag flips or a source starts or stops failing, so
+/// it's left to the ETag to tell whether those are still current.
+pub fn last_modified(fetched: SystemTime, stale: bool, partial: bool) -> Option<SystemTime> {
+    (!stale && !partial).then_some(fetched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    fn body() -> serde_json::Value {
+        serde_json::json! {{ "stops": [] }}
+    }
+
+    /// Fetched half a second into a second, which `Last-Modified` can't express
+    fn fetched() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(1_800_000_000_500)
+    }
+
+    fn etag() -> String {
+        let res = respond(&TestRequest::default().to_http_request(), Some(fetched()), body());
+        res.headers().get(header::ETAG).unwrap().to_str().unwrap().to_owned()
+    }
+
+    fn status(headers: &[(header::HeaderName, &str)]) -> StatusCode {
+        let req = headers.iter()
+            .fold(TestRequest::default(), |req, (name, value)| req.insert_header((name.clone(), *value)))
+            .to_http_request();
+
+        respond(&req, Some(fetched()), body()).status()
+    }
+
+    #[test]
+    fn etag_is_stable() {
+        assert_eq!(etag(), format!("\"{hash:016x}\"", hash = fnv1a(br#"{"stops":[]}"#)));
+    }
+
+    #[test]
+    fn without_validators() {
+        let res = respond(&TestRequest::default().to_http_request(), Some(fetched()), body());
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(header::LAST_MODIFIED).unwrap(), "Fri, 15 Jan 2027 08:00:00 GMT");
+        assert_eq!(res.headers().get(header::CONTENT_TYPE).unwrap(), "application/json");
+    }
+
+    #[test]
+    fn if_none_match() {
+        assert_eq!(status(&[(header::IF_NONE_MATCH, &etag())]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status(&[(header::IF_NONE_MATCH, "*")]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status(&[(header::IF_NONE_MATCH, "\"other\"")]), StatusCode::OK);
+    }
+
+    #[test]
+    fn if_none_match_compares_weakly() {
+        let weak = format!("\"other\", W/{etag}", etag = etag());
+        assert_eq!(status(&[(header::IF_NONE_MATCH, &weak)]), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn if_none_match_takes_precedence() {
+        let later = "Sat, 16 Jan 2027 08:00:00 GMT";
+
+        assert_eq!(status(&[(header::IF_NONE_MATCH, "\"other\""), (header::IF_MODIFIED_SINCE, later)]), StatusCode::OK);
+        assert_eq!(status(&[(header::IF_NONE_MATCH, &etag()), (header::IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT")]), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn if_modified_since_has_second_precision() {
+        assert_eq!(status(&[(header::IF_MODIFIED_SINCE, "Fri, 15 Jan 2027 08:00:00 GMT")]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status(&[(header::IF_MODIFIED_SINCE, "Fri, 15 Jan 2027 07:59:59 GMT")]), StatusCode::OK);
+    }
+
+    #[test]
+    fn stale_or_partial_bodies_are_not_modified_since() {
+        let since = || TestRequest::default().insert_header((header::IF_MODIFIED_SINCE, "Sat, 16 Jan 2027 08:00:00 GMT")).to_http_request();
+        let stale = serde_json::json! {{ "stale": true, "stops": [] }};
+
+        let res = respond(&since(), last_modified(fetched(), true, false), stale);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res.headers().contains_key(header::LAST_MODIFIED));
+
+        let res = respond(&since(), last_modified(fetched(), false, true), body());
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = respond(&since(), last_modified(fetched(), false, false), body());
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    }
+}
@@ -5,5 +5,9 @@ use actix_web::web;
 
 #[actix_web::get("/config")]
 pub async fn config(config: web::Data<Config>) -> Result<impl Responder> {
+    let mut config = Config::clone(&config);
+    config.admin.token = None;
+    config.cache.redis = None;
+
     Ok(HttpResponse::Ok().json(config))
 }
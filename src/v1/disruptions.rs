@@ -0,0 +1,102 @@
+use crate::state::Caches;
+use crate::transit::Disruptions;
+use crate::transit::Transit;
+use crate::v1::buses;
+use crate::v1::buses::BoardQuery;
+use crate::v1::buses_schema::DepartureBoardStop;
+use crate::v1::conditional;
+use crate::Result;
+use actix_web::web;
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use common::config::Config;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Disruption {
+    pub id: String,
+    pub line: String,
+    pub content: String,
+
+    /// Configured stops at which the line reports this notice
+    pub stops: BTreeSet<String>,
+    pub first_seen: Option<SystemTime>,
+}
+
+/// Every disruption notice currently reported at any configured stop, once per line
+#[actix_web::get("/disruptions")]
+pub async fn disruptions(req: HttpRequest, cfg: web::Data<Config>, caches: web::Data<Caches>, transit: web::Data<Transit>) -> Result<impl Responder> {
+    let stops = buses::all_stops(&cfg, &caches, &transit, &BoardQuery::default()).await?;
+    let fetched = stops.fetched();
+
+    let notices = collect(stops.values.iter().map(|(stop, list)| (stop, list.value.as_slice())), transit.disruptions());
+
+    let last_modified = conditional::last_modified(fetched, stops.stale(), !stops.errors.is_empty());
+
+    Ok(conditional::respond(&req, last_modified, serde_json::json! {{
+        "time": fetched,
+        "stale": stops.stale(),
+        "errors": stops.failures(),
+        "disruptions": notices,
+    }}))
+}
+
+/// The notices on the boards of each stop, once per line and notice
+fn collect<'a>(boards: impl IntoIterator<Item = (&'a String, &'a [DepartureBoardStop])>, seen: &Disruptions) -> Vec<Disruption> {
+    let mut notices = BTreeMap::new();
+
+    for (stop, departures) in boards {
+        for departure in departures {
+            for hint in &departure.hints {
+                notices.entry((departure.line.clone(), hint.id.clone()))
+                    .or_insert_with(|| Disruption {
+                        id: hint.id.clone(),
+                        line: departure.line.clone(),
+                        content: hint.content.clone(),
+                        stops: BTreeSet::new(),
+                        first_seen: seen.first_seen(&hint.id),
+                    })
+                    .stops
+                    .insert(stop.clone());
+            }
+        }
+    }
+
+    notices.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::buses_schema::DepartureHint;
+
+    fn departure(line: &str, hint: &DepartureHint) -> DepartureBoardStop {
+        let mut departure = DepartureBoardStop::example(line, "Hbf", "2030-01-02T14:00:00+01:00", "2030-01-02T14:00:00+01:00");
+        departure.hints.push(hint.clone());
+        departure
+    }
+
+    #[test]
+    fn lists_each_line_once() {
+        let works = DepartureHint::new("Bauarbeiten");
+        let (a, b) = ("a".to_owned(), "b".to_owned());
+
+        let board_a = [departure("5", &works), departure("5", &works), departure("7", &works)];
+        let board_b = [departure("5", &works)];
+
+        let seen = Disruptions::default();
+        seen.record("a", &board_a);
+
+        let notices = collect([(&a, &board_a[..]), (&b, &board_b[..])], &seen);
+        let lines = notices.iter()
+            .map(|disruption| (disruption.line.as_str(), disruption.stops.iter().map(String::as_str).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines, [("5", vec!["a", "b"]), ("7", vec!["a"])]);
+        assert!(notices.iter().all(|disruption| disruption.id == works.id && disruption.first_seen.is_some()));
+    }
+}
@@ -1,18 +1,39 @@
-use actix_web::{HttpResponse, Responder};
+use actix_web::{web, HttpResponse, Responder};
+use crate::Error;
 
-mod buses;
-mod weather;
+mod admin;
+mod board;
+pub(crate) mod buses;
+pub(crate) mod weather;
+mod conditional;
 mod config;
-mod weather_schema;
-mod buses_schema;
+mod disruptions;
+mod partial;
+mod status;
+mod stops;
+mod trips;
+pub(crate) mod weather_schema;
+pub(crate) mod buses_schema;
+pub(crate) mod trips_schema;
+pub(crate) mod stops_schema;
 
 pub fn v1() -> actix_web::Scope {
     actix_web::web::scope("/v1")
+        // Malformed query parameters get the same problem details as every other error
+        .app_data(web::QueryConfig::default().error_handler(|err, _| Error::BadRequest(err.to_string()).into()))
         .service(version)
         .service(config::config)
+        .service(status::status)
+        .service(admin::admin())
         .service(weather::current)
         .service(weather::forecast)
         .service(buses::buses)
+        .service(buses::single_stop)
+        .service(board::board)
+        .service(disruptions::disruptions)
+        .service(trips::trips)
+        .service(stops::search)
+        .service(stops::nearby)
 }
 
 #[actix_web::get("/version")]
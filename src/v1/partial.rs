@@ -0,0 +1,57 @@
+use crate::cache::Cached;
+use crate::Error;
+use crate::Result;
+use serde::Serialize;
+use std::time::SystemTime;
+
+/// Values fetched from several sources at once. Sources which failed are reported next to the others rather than
+/// failing the whole response, unless none of them succeeded.
+pub struct Partial<Key, Value> {
+    pub values: Vec<(Key, Cached<Value>)>,
+    pub errors: Vec<(Key, Error)>,
+}
+
+/// A failed source, as listed under `errors`
+#[derive(Serialize)]
+pub struct Failure<'a, Key> {
+    pub source: &'a Key,
+
+    #[serde(flatten)]
+    pub error: &'a Error,
+}
+
+impl<Key, Value> Partial<Key, Value> {
+    pub fn collect(results: impl IntoIterator<Item = (Key, Result<Cached<Value>>)>) -> Result<Self> {
+        let mut partial = Self { values: Vec::new(), errors: Vec::new() };
+
+        for (key, result) in results {
+            match result {
+                Ok(value) => partial.values.push((key, value)),
+                Err(err) => partial.errors.push((key, err)),
+            }
+        }
+
+        match partial.values.is_empty() {
+            true if !partial.errors.is_empty() => Err(partial.errors.swap_remove(0).1),
+            _ => Ok(partial),
+        }
+    }
+
+    pub fn stale(&self) -> bool {
+        self.values.iter().any(|(_, value)| value.stale)
+    }
+
+    /// When the most recent of the values was fetched
+    pub fn fetched(&self) -> SystemTime {
+        self.values.iter()
+            .map(|(_, value)| value.fetched)
+            .max()
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    }
+
+    pub fn failures(&self) -> Vec<Failure<'_, Key>> {
+        self.errors.iter()
+            .map(|(source, error)| Failure { source, error })
+            .collect()
+    }
+}
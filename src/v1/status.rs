@@ -0,0 +1,14 @@
+use crate::prefetch::Prefetcher;
+use crate::Result;
+use actix_web::web;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use std::time::SystemTime;
+
+#[actix_web::get("/status")]
+pub async fn status(prefetcher: web::Data<Prefetcher>) -> Result<impl Responder> {
+    Ok(HttpResponse::Ok().json(serde_json::json! {{
+        "time": SystemTime::now(),
+        "sources": prefetcher.status().await,
+    }}))
+}
@@ -0,0 +1,163 @@
+use crate::state::Caches;
+use crate::transit::Transit;
+use crate::Error;
+use crate::Result;
+use actix_web::web;
+use actix_web::HttpResponse;
+use common::config::Config;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchQuery {
+    /// Name of the stop, or part of it
+    q: String,
+
+    /// How many stops to return at most
+    #[serde(default = "default_limit")]
+    limit: u32,
+
+    /// Name of an entry in `[providers]` to search instead of the default EFA instance
+    #[serde(default)]
+    provider: Option<String>,
+}
+
+/// Passed on to EFA as the size of its hit list
+const MAX_SEARCH_LIMIT: u32 = 100;
+
+fn default_limit() -> u32 {
+    20
+}
+
+/// Stops matching a name, so that they can be picked without running `stopfinder` on the server
+#[actix_web::get("/stops/search")]
+pub async fn search(query: web::Query<SearchQuery>, transit: web::Data<Transit>) -> Result<HttpResponse> {
+    if query.q.trim().is_empty() {
+        return Err(Error::BadRequest("`q` must not be empty".to_owned()));
+    }
+
+    if query.limit > MAX_SEARCH_LIMIT {
+        return Err(Error::BadRequest(format!("`limit` may be at most {MAX_SEARCH_LIMIT}")));
+    }
+
+    let Some(provider) = transit.named(query.provider.as_deref()) else {
+        return Err(unknown_provider(query.provider.as_deref()));
+    };
+
+    let stops = provider.search(query.q.trim(), query.limit).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json! {{
+        "stops": stops,
+    }}))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NearbyQuery {
+    /// Defaults to the site's coordinate from `[weather]`
+    #[serde(default)]
+    lat: Option<f64>,
+
+    #[serde(default)]
+    lon: Option<f64>,
+
+    /// In metres
+    #[serde(default = "default_radius")]
+    radius: u32,
+
+    #[serde(default = "default_nearby_limit")]
+    limit: u32,
+
+    #[serde(default)]
+    provider: Option<String>,
+}
+
+/// Each nearby stop costs another departure monitor request to look up its lines, so only few may be asked for
+const MAX_NEARBY_LIMIT: u32 = 25;
+
+/// In metres
+const MAX_RADIUS: u32 = 5_000;
+
+fn default_radius() -> u32 {
+    500
+}
+
+fn default_nearby_limit() -> u32 {
+    10
+}
+
+/// Stops around the site, nearest first, to propose when setting up a new dashboard
+#[actix_web::get("/stops/nearby")]
+pub async fn nearby(query: web::Query<NearbyQuery>, cfg: web::Data<Config>, caches: web::Data<Caches>, transit: web::Data<Transit>) -> Result<HttpResponse> {
+    if query.limit > MAX_NEARBY_LIMIT {
+        return Err(Error::BadRequest(format!("`limit` may be at most {MAX_NEARBY_LIMIT}")));
+    }
+
+    if query.radius > MAX_RADIUS {
+        return Err(Error::BadRequest(format!("`radius` may be at most {MAX_RADIUS} metres")));
+    }
+
+    let Some(provider) = transit.named(query.provider.as_deref()) else {
+        return Err(unknown_provider(query.provider.as_deref()));
+    };
+
+    let latitude = query.lat.unwrap_or(cfg.weather.latitude.into());
+    let longitude = query.lon.unwrap_or(cfg.weather.longitude.into());
+
+    let mut stops = provider.nearby(latitude, longitude, query.radius, query.limit).await?;
+
+    let lines = futures::future::join_all(stops.iter().map(|stop| {
+        let provider = provider.clone();
+        let gid = stop.gid.clone();
+
+        caches.lines.get(stop.gid.clone(), move || async move { provider.lines(&gid).await })
+    }))
+    .await;
+
+    for (stop, lines) in stops.iter_mut().zip(lines) {
+        stop.lines = lines.map(|lines| lines.value).unwrap_or_else(|err| {
+            log::warn!("Failed to look up the lines serving {gid}: {err}", gid = stop.gid);
+            Vec::new()
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json! {{
+        "latitude": latitude,
+        "longitude": longitude,
+        "stops": stops,
+    }}))
+}
+
+fn unknown_provider(name: Option<&str>) -> Error {
+    Error::NotFound(format!("Provider '{name}' isn't configured", name = name.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpClient;
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use actix_web::App;
+
+    async fn status(uri: &str) -> StatusCode {
+        let config = Config::default();
+        let caches = Caches::new(&config.cache).await.expect("Failed to set up caches");
+        let http = HttpClient::new(&config.http).expect("Failed to set up HTTP client");
+        let transit = Transit::new(&config, &http).expect("Failed to set up providers");
+
+        let app = test::init_service(App::new()
+            .app_data(web::Data::new(config))
+            .app_data(web::Data::new(caches))
+            .app_data(web::Data::new(transit))
+            .service(search)
+            .service(nearby)).await;
+
+        test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await.status()
+    }
+
+    #[actix_web::test]
+    async fn limits_are_bounded() {
+        assert_eq!(status("/stops/search?q=Hbf&limit=101").await, StatusCode::BAD_REQUEST);
+        assert_eq!(status("/stops/nearby?limit=26").await, StatusCode::BAD_REQUEST);
+        assert_eq!(status("/stops/nearby?radius=5001").await, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// A stop as found by searching for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stop {
+    pub name: String,
+
+    /// Global stop ID, as used for `point` in the config
+    pub gid: String,
+
+    /// Town or district the stop lies in
+    pub place: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+
+    /// Metres from the coordinate searched around
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<u32>,
+
+    /// Lines serving the stop. Only looked up for nearby stops.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<String>,
+}
@@ -0,0 +1,98 @@
+use crate::state::Caches;
+use crate::transit::Route;
+use crate::transit::Transit;
+use crate::v1::conditional;
+use crate::v1::partial::Partial;
+use crate::v1::trips_schema::Journey;
+use crate::Error;
+use crate::Result;
+use actix_web::web;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use common::config::Config;
+use common::prelude::tokio::task::JoinSet;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Which routes to plan. Every configured stop to every destination by default.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct TripsQuery {
+    /// Name of a configured destination
+    destination: Option<String>,
+
+    /// Point of a configured stop
+    origin: Option<String>,
+}
+
+/// A configured stop and the name of a destination
+#[derive(Clone, Serialize)]
+pub struct Endpoints {
+    pub origin: String,
+    pub destination: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Connections {
+    #[serde(flatten)]
+    pub endpoints: Endpoints,
+    pub journeys: Vec<Journey>,
+}
+
+/// The next connections from the configured stops to the configured destinations
+#[actix_web::get("/trips")]
+pub async fn trips(req: HttpRequest, query: web::Query<TripsQuery>, cfg: web::Data<Config>, caches: web::Data<Caches>, transit: web::Data<Transit>) -> Result<HttpResponse> {
+    let destinations = cfg.destination.iter()
+        .filter(|destination| query.destination.as_ref().is_none_or(|name| *name == destination.name))
+        .collect::<Vec<_>>();
+
+    let origins = cfg.departure.iter()
+        .filter(|stop| query.origin.as_ref().is_none_or(|point| *point == stop.point))
+        .collect::<Vec<_>>();
+
+    if destinations.is_empty() {
+        return Err(Error::NotFound("No such destination is configured".to_owned()));
+    }
+
+    if origins.is_empty() {
+        return Err(Error::NotFound("No such stop is configured".to_owned()));
+    }
+
+    let mut tasks = JoinSet::new();
+
+    for (index, (origin, destination)) in origins.iter()
+        .flat_map(|origin| destinations.iter().map(move |destination| (origin, destination)))
+        .enumerate()
+    {
+        let route = Route {
+            origin: origin.point.clone(),
+            destination: destination.point.clone(),
+            connections: cfg.trips.connections,
+        };
+
+        let endpoints = Endpoints { origin: origin.point.clone(), destination: destination.name.clone() };
+        let (caches, transit) = (caches.clone(), transit.clone());
+
+        tasks.spawn(async move {
+            let journeys = caches.trips.get(route.clone(), || transit.trips(route)).await;
+            (index, endpoints, journeys)
+        });
+    }
+
+    let mut routes = tasks.join_all().await;
+    routes.sort_by_key(|(index, ..)| *index);
+
+    let routes = Partial::collect(routes.into_iter().map(|(_, endpoints, journeys)| (endpoints, journeys)))?;
+    let fetched = routes.fetched();
+
+    let last_modified = conditional::last_modified(fetched, routes.stale(), !routes.errors.is_empty());
+
+    Ok(conditional::respond(&req, last_modified, serde_json::json! {{
+        "time": fetched,
+        "stale": routes.stale(),
+        "errors": routes.failures(),
+        "trips": routes.values.into_iter()
+            .map(|(endpoints, journeys)| Connections { endpoints, journeys: journeys.value })
+            .collect::<Vec<_>>(),
+    }}))
+}
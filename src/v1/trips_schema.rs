@@ -0,0 +1,137 @@
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+use crate::v1::buses_schema::Platform;
+use crate::v1::buses_schema::TransportMode;
+
+/// The parts of an EFA trip response the API uses
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TripSchema {
+    /// `null` if no connection was found
+    #[serde(default)]
+    pub(crate) trips: Option<Vec<EfaTrip>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EfaTrip {
+    #[serde(default)]
+    duration: String,
+    #[serde(default)]
+    interchange: String,
+    pub(crate) legs: Vec<EfaLeg>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EfaLeg {
+    #[serde(default)]
+    time_minute: String,
+    pub(crate) points: Vec<EfaLegPoint>,
+    pub(crate) mode: EfaLegMode,
+    #[serde(default)]
+    pub(crate) realtime_status: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EfaLegPoint {
+    pub(crate) name: String,
+    /// `departure` or `arrival`
+    pub(crate) usage: String,
+    #[serde(default)]
+    pub(crate) platform_name: String,
+    pub(crate) date_time: EfaLegDateTime,
+    #[serde(rename = "ref", default)]
+    pub(crate) point_ref: EfaLegRef,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EfaLegDateTime {
+    /// `dd.mm.yyyy`
+    pub(crate) date: String,
+    /// `hh:mm`
+    pub(crate) time: String,
+    #[serde(default)]
+    pub(crate) rt_date: Option<String>,
+    #[serde(default)]
+    pub(crate) rt_time: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EfaLegRef {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    pub(crate) platform: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EfaLegMode {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    pub(crate) number: String,
+    #[serde(default)]
+    pub(crate) symbol: String,
+    #[serde(rename = "type", default)]
+    pub(crate) mot_type: String,
+    #[serde(default)]
+    pub(crate) destination: String,
+}
+
+/// A connection from one of the configured stops to a destination
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Journey {
+    pub departure: chrono::DateTime<FixedOffset>,
+    pub arrival: chrono::DateTime<FixedOffset>,
+
+    #[serde(rename = "durationMinutes")]
+    pub duration_minutes: i64,
+
+    /// How often one has to change vehicles. Walking between stops doesn't count.
+    pub transfers: usize,
+
+    pub legs: Vec<Leg>,
+}
+
+/// A part of a journey spent in a single vehicle, or on foot
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Leg {
+    pub mode: TransportMode,
+
+    /// Absent for footpaths
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<String>,
+
+    pub origin: Stopover,
+    pub destination: Stopover,
+
+    /// How late the leg sets off according to realtime data. Absent without realtime data.
+    #[serde(default, rename = "delayMinutes")]
+    pub delay_minutes: Option<i64>,
+
+    #[serde(default, rename = "isRealtime")]
+    pub is_realtime: bool,
+
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// Where and when a leg starts or ends
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Stopover {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+
+    pub planned: chrono::DateTime<FixedOffset>,
+    pub expected: chrono::DateTime<FixedOffset>,
+}
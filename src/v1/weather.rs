@@ -1,12 +1,18 @@
 use crate::cache::Cache;
+use crate::cache::Cached;
+use crate::http::HttpClient;
+use crate::state::Caches;
+use crate::v1::conditional;
+use crate::v1::partial::Failure;
 use crate::v1::weather_schema::PresentWeather;
 use crate::v1::weather_schema::WeatherDay;
 use crate::v1::weather_schema::WeatherResponse;
 use crate::v1::weather_schema::WeatherSchema;
 use crate::v1::weather_schema::WeatherState;
+use crate::Error;
 use crate::Result;
 use actix_web::web;
-use actix_web::HttpResponse;
+use actix_web::HttpRequest;
 use actix_web::Responder;
 use common::config::Config;
 use common::config::WeatherConfig;
@@ -14,9 +20,6 @@ use common::prelude::*;
 use reqwest::Url;
 use serde::Deserialize;
 use serde::Serialize;
-use std::io;
-use std::io::Error;
-use std::time::Duration;
 use std::time::SystemTime;
 
 const WEATHER_API: &'static str = "https://api.open-meteo.com/v1/forecast";
@@ -27,29 +30,35 @@ pub struct ForecastParams {
     days: Option<u16>,
 }
 
-static WEATHER_CACHE: Cache<ForecastParams, WeatherState> = Cache::new(Duration::from_secs(30));
-static CITY_NAME: Cache<Coordinate, String> = Cache::new(Duration::from_hours(12));
-type Coordinate = String;
+pub type Coordinate = String;
 
 #[actix_web::get("/current")]
-pub async fn current(query: web::Query<ForecastParams>, cfg: web::Data<Config>) -> Result<impl Responder> {
-    let weather = get_weather(query.0.clone(), &cfg).await?;
+pub async fn current(req: HttpRequest, query: web::Query<ForecastParams>, cfg: web::Data<Config>, caches: web::Data<Caches>, http: web::Data<HttpClient>) -> Result<impl Responder> {
+    let weather = get_weather(&caches, &http, query.0.clone(), &cfg).await?;
 
-    Ok(HttpResponse::Ok().json(serde_json::json! {{
+    let last_modified = conditional::last_modified(weather.fetched, weather.stale, weather.city_error.is_some());
+
+    Ok(conditional::respond(&req, last_modified, serde_json::json! {{
         "time": weather.time,
+        "stale": weather.stale,
         "city": weather.city,
+        "errors": weather.failures(),
         "is_day": weather.response.is_day,
         "current": weather.response.current,
     }}))
 }
 
 #[actix_web::get("/forecast")]
-pub async fn forecast(query: web::Query<ForecastParams>, cfg: web::Data<Config>) -> Result<impl Responder> {
-    let weather = get_weather(query.0.clone(), &cfg).await?;
+pub async fn forecast(req: HttpRequest, query: web::Query<ForecastParams>, cfg: web::Data<Config>, caches: web::Data<Caches>, http: web::Data<HttpClient>) -> Result<impl Responder> {
+    let weather = get_weather(&caches, &http, query.0.clone(), &cfg).await?;
+
+    let last_modified = conditional::last_modified(weather.fetched, weather.stale, weather.city_error.is_some());
 
-    Ok(HttpResponse::Ok().json(serde_json::json! {{
+    Ok(conditional::respond(&req, last_modified, serde_json::json! {{
         "time": weather.time,
+        "stale": weather.stale,
         "city": weather.city,
+        "errors": weather.failures(),
         "forecast": weather.response.daily,
     }}))
 }
@@ -77,89 +86,100 @@ struct CityResponse {
     plus_code: String,
 }
 
-async fn get_city_name(lat: f64, long: f64) -> Result<String> {
-    let qs = serde_qs::to_string(&(lat, long)).map_err(Error::other)?;
-
-    CITY_NAME.get(qs.clone(), async || {
-        let api = match reqwest::ClientBuilder::new().build() {
-            Ok(client) => client,
-            Err(err) => return Err(io::Error::other(err)),
-        };
+async fn get_city_name(city_names: &Cache<Coordinate, String>, http: &HttpClient, lat: f64, long: f64) -> Result<String> {
+    let qs = serde_qs::to_string(&(lat, long)).map_err(Error::internal)?;
+    let http = http.clone();
 
-        let mut uri = Url::parse(CITY_NAME_API).map_err(io::Error::other)?;
+    city_names.get(qs.clone(), move || async move {
+        let mut uri = Url::parse(CITY_NAME_API).map_err(Error::internal)?;
 
         let query = serde_qs::to_string(&LatLongCityResolverQueryString {
             latitude: lat,
             longitude: long,
             locality_language: "default".into(),
         })
-        .map_err(io::Error::other)?;
+        .map_err(Error::internal)?;
 
         uri.set_query(Some(&query));
 
-        match api.get(uri).header("Accept", "application/json").send().await {
-            Ok(req) => match req.json::<CityResponse>().await {
-                Ok(res) => {
-                    log::debug!("Resolved to city: {city}, {locality}", city=res.city, locality=res.locality);
-                    Ok(format!("{city}, {locality}", city = res.city, locality = res.locality))
-                },
-                Err(err) => {
-                    log::error!("Response Error: {err:?}");
-                    return Err(std::io::Error::other(err));
-                }
+        match http.get::<CityResponse>(uri).await {
+            Ok(res) => {
+                log::debug!("Resolved to city: {city}, {locality}", city=res.city, locality=res.locality);
+                Ok(format!("{city}, {locality}", city = res.city, locality = res.locality))
             },
             Err(err) => {
-                log::error!("Reqwest Error: {err:?}");
-                return Err(std::io::Error::other(err));
+                log::error!("City name Error: {err}");
+                Err(err)
             }
         }
-    }).await
+    }).await.map(|city| city.value)
 }
 
-async fn get_weather(query: ForecastParams, cfg: &Config) -> Result<WeatherState> {
-    WEATHER_CACHE.get(query.clone(), async || {
-        let api = match reqwest::ClientBuilder::new().build() {
-            Ok(client) => client,
-            Err(err) => return Err(io::Error::other(err)),
-        };
-
-        let mut uri =
-            Url::parse(WEATHER_API).map_err(io::Error::other)?;
-
-        let query = serde_qs::to_string(&WeatherConfig {
-            forecast_days: query.days.or(cfg.weather.forecast_days),
-            config: serde_json::json! {{
-                "daily": ["weather_code", "temperature_2m_max", "temperature_2m_min", "precipitation_sum", "wind_speed_10m_max"],
-                "current": ["temperature_2m", "relative_humidity_2m", "precipitation", "weather_code", "wind_speed_10m", "is_day"]
-            }},
-            ..cfg.weather.clone()
-        })
-            .map_err(io::Error::other)?;
+async fn get_weather(caches: &Caches, http: &HttpClient, query: ForecastParams, cfg: &Config) -> Result<Cached<WeatherState>> {
+    let (cfg, http) = (cfg.clone(), http.clone());
+    let city_names = caches.city_names.clone();
 
-        uri.set_query(Some(&query));
+    caches.weather.get(query.clone(), move || fetch_weather(city_names, http, query, cfg)).await
+}
 
-        log::debug!("URI: {uri:?}", uri = uri.to_string());
+/// Fetches the configured location's weather into the cache, regardless of whether the cached one has expired yet.
+pub(crate) async fn refresh(caches: &Caches, http: &HttpClient, cfg: &Config) -> Result<()> {
+    let (cfg, http) = (cfg.clone(), http.clone());
+    let city_names = caches.city_names.clone();
 
-        let req = match api.get(uri).header("Accept", "application/json").send().await {
-            Ok(req) => req,
-            Err(err) => {
-                log::error!("Reqwest Error: {err:?}");
-                return Err(std::io::Error::other(err));
-            }
-        };
+    caches.weather.refresh(ForecastParams::default(), move || fetch_weather(city_names, http, ForecastParams::default(), cfg)).await
+        .map(|_| ())
+}
 
-        match req.json::<WeatherSchema>().await {
-            Ok(res) => convert_to_weather_state(res).await
-                .ok_or(Error::other("Not all data was received")),
-            Err(err) => {
-                log::error!("Response Error: {err:?}");
-                return Err(std::io::Error::other(err));
-            }
+async fn fetch_weather(city_names: Cache<Coordinate, String>, http: HttpClient, query: ForecastParams, cfg: Config) -> Result<WeatherState> {
+    let mut uri =
+        Url::parse(WEATHER_API).map_err(Error::internal)?;
+
+    let query = serde_qs::to_string(&WeatherConfig {
+        forecast_days: query.days.or(cfg.weather.forecast_days),
+        config: serde_json::json! {{
+            "daily": ["weather_code", "temperature_2m_max", "temperature_2m_min", "precipitation_sum", "wind_speed_10m_max"],
+            "current": ["temperature_2m", "relative_humidity_2m", "precipitation", "weather_code", "wind_speed_10m", "is_day"]
+        }},
+        ..cfg.weather.clone()
+    })
+        .map_err(Error::internal)?;
+
+    uri.set_query(Some(&query));
+
+    log::debug!("URI: {uri:?}", uri = uri.to_string());
+
+    match http.get::<WeatherSchema>(uri).await {
+        Ok(res) => convert_to_weather_state(&city_names, &http, res).await
+            .ok_or(Error::SchemaMismatch("Not all data was received".to_owned())),
+        Err(err) => {
+            log::error!("Weather Error: {err}");
+            Err(err)
+        }
+    }
+}
+
+/// The city at the coordinates, or the coordinates themselves along with the reason if the geocoder can't be reached
+async fn city_name(city_names: &Cache<Coordinate, String>, http: &HttpClient, lat: f64, long: f64) -> (String, Option<Error>) {
+    match get_city_name(city_names, http, lat, long).await {
+        Ok(city) => (city, None),
+        Err(err) => {
+            log::warn!("Falling back to coordinates as city name: {err}");
+            (format!("{lat:.4}, {long:.4}"), Some(err))
         }
-    }).await
+    }
 }
 
-async fn convert_to_weather_state(incoming: WeatherSchema) -> Option<WeatherState> {
+impl WeatherState {
+    /// Sources which failed without failing the response, as listed by [`Partial`](crate::v1::partial::Partial)
+    pub fn failures(&self) -> Vec<Failure<'_, &'static str>> {
+        self.city_error.iter()
+            .map(|error| Failure { source: &"geocoder", error })
+            .collect()
+    }
+}
+
+async fn convert_to_weather_state(city_names: &Cache<Coordinate, String>, http: &HttpClient, incoming: WeatherSchema) -> Option<WeatherState> {
     // &daily=weather_code,temperature_2m_max,temperature_2m_min,precipitation_sum,wind_speed_10m_max,relative_humidity_2m
     let daily = (0..incoming.daily.get("weather_code")?.as_array()?.len())
         .map(|a| {
@@ -199,11 +219,12 @@ async fn convert_to_weather_state(incoming: WeatherSchema) -> Option<WeatherStat
         })
         .collect::<Option<Vec<_>>>()?;
 
+    let (city, city_error) = city_name(city_names, http, incoming.latitude, incoming.longitude).await;
+
     Some(WeatherState {
         time: SystemTime::now(),
-        city: get_city_name(incoming.latitude, incoming.longitude)
-            .await
-            .ok()?,
+        city,
+        city_error,
         response: WeatherResponse {
             is_day: incoming.current.get("is_day")?.as_u64()? == 1,
 
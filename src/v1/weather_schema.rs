@@ -1,3 +1,4 @@
+use crate::Error;
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
 
@@ -6,6 +7,11 @@ nestify::nest! {
     pub struct WeatherState {
         pub time: SystemTime,
         pub city: String,
+
+        /// Why `city` only gives the coordinates
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub city_error: Option<Error>,
+
         pub response: pub struct WeatherResponse {
             pub is_day: bool,
             pub current: pub struct WeatherDay {
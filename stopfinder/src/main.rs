@@ -1,7 +1,6 @@
 #![feature(iter_intersperse)]
 
 mod io;
-mod schema;
 
 use common::prelude::*;
 use std::io::Error;
@@ -10,11 +9,9 @@ use std::path::PathBuf;
 
 mod prelude {
     pub use crate::io::*;
-    pub use crate::schema::*;
+    pub use common::efa::*;
 }
 
-const STANDARD_API: &'static str = "https://www.efa-bw.de/mobidata-bw/";
-
 #[tokio::main]
 pub async fn main() {
     env_logger::init();
@@ -22,12 +19,12 @@ pub async fn main() {
     println!(
         r#"
 Willkommen zum Haltestellensuchassistentsprogramm der Azubitafel
-Geben Sie den Namen einer Haltestelle ein.
+Geben Sie den Namen einer Haltestelle ein, oder Koordinaten (Breite,Länge) um Haltestellen in der Nähe zu finden.
 "#
     );
 
     let api_url: reqwest::Url = std::env::var("BUSFAHRTEN_API")
-        .unwrap_or(STANDARD_API.to_string())
+        .unwrap_or(EFA_API.to_string())
         .parse()
         .expect("Ungültige API URL");
 
@@ -38,45 +35,38 @@ Geben Sie den Namen einer Haltestelle ein.
     'outer: loop {
         match prelude::prompt("Haltestelle: # ").await {
             Ok(search) => {
-                let mut api_url = api_url.clone();
-                api_url.set_path(
-                    PathBuf::from(api_url.path())
-                        .join("XML_STOPFINDER_REQUEST")
-                        .to_str()
-                        .expect("Ungültiger Pfad angegeben"),
-                );
-
-                let req = match client
-                    .request(reqwest::Method::GET, api_url)
-                    .query(&prelude::SearchParams::default().search(&search))
-                    .send()
-                    .await
-                {
-                    Ok(req) => req,
+                let points = match coordinate(&search) {
+                    Some((latitude, longitude)) => nearby(&client, &api_url, latitude, longitude).await,
+                    None => find(&client, &api_url, &search).await,
+                };
+
+                let points = match points {
+                    Ok(points) => points,
                     Err(err) => {
                         log::error!("Ein Fehler ist aufgetreten: {err:#?}");
                         continue;
                     }
                 };
 
-                if let Ok(stops) = req.json::<prelude::StopResponse>().await && stops.stops.points.len() > 0 {
-                    let matches = stops
-                        .stops
-                        .points
+                if !points.is_empty() {
+                    let matches = points
                         .iter()
                         .enumerate()
-                        .map(|(a, point)| format!("{id}: {name}", id = a + 1, name = &point.name))
+                        .map(|(a, (_, label))| format!("{id}: {label}", id = a + 1))
                         .intersperse("\n  ".to_owned())
                         .collect::<String>();
 
                     println!("Es wurden folgende Treffer gefunden: \n  {matches}\nBitte geben Sie die Nummer der gewünschten Haltestelle an");
 
                     loop {
-                        let index = prelude::prompt(format!(" (1-{len}) # ", len=stops.stops.points.len())).await
+                        let index = prelude::prompt(format!(" (1-{len}) # ", len=points.len())).await
                             .and_then(|str| str.parse::<usize>().map_err(Error::other));
 
-                        if let Ok(Some(point)) = index.map(|index| stops.stops.points.get(index - 1)) {
-                            save_point(point).await;
+                        if let Ok(Some((point, _))) = index.map(|index| points.get(index - 1)) {
+                            if let Err(err) = save_point(point, &api_url).await {
+                                log::error!("Speichern fehlgeschlagen: {err}");
+                                std::process::exit(1);
+                            }
 
                             log::info!("Fertig");
 
@@ -95,7 +85,90 @@ Geben Sie den Namen einer Haltestelle ein.
     }
 }
 
-async fn save_point(point: &prelude::Point) -> Result<()> {
+fn endpoint(api_url: &reqwest::Url, name: &str) -> reqwest::Url {
+    let mut url = api_url.clone();
+    url.set_path(
+        PathBuf::from(url.path())
+            .join(name)
+            .to_str()
+            .expect("Ungültiger Pfad angegeben"),
+    );
+
+    url
+}
+
+/// Input like `48.5227,9.0522` is a coordinate to look for stops around
+fn coordinate(search: &str) -> Option<(f64, f64)> {
+    let (latitude, longitude) = search.split_once(',')?;
+    Some((latitude.trim().parse().ok()?, longitude.trim().parse().ok()?))
+}
+
+async fn find(client: &reqwest::Client, api_url: &reqwest::Url, search: &str) -> Result<Vec<(prelude::Point, String)>> {
+    let stops = client
+        .request(reqwest::Method::GET, endpoint(api_url, "XML_STOPFINDER_REQUEST"))
+        .query(&prelude::SearchParams::default().search(search))
+        .send()
+        .await
+        .map_err(Error::other)?
+        .json::<prelude::StopResponse>()
+        .await
+        .map_err(Error::other)?;
+
+    Ok(stops.stops.points.into_iter()
+        .map(|point| {
+            let label = point.name.clone();
+            (point, label)
+        })
+        .collect())
+}
+
+/// Stops around a coordinate, nearest first, labelled with their distance and the lines serving them
+async fn nearby(client: &reqwest::Client, api_url: &reqwest::Url, latitude: f64, longitude: f64) -> Result<Vec<(prelude::Point, String)>> {
+    let mut pins = client
+        .request(reqwest::Method::GET, endpoint(api_url, "XML_COORD_REQUEST"))
+        .query(&prelude::CoordParams::default().around(latitude, longitude))
+        .send()
+        .await
+        .map_err(Error::other)?
+        .json::<prelude::CoordResponse>()
+        .await
+        .map_err(Error::other)?
+        .pins;
+
+    pins.sort_by_key(|pin| pin.distance().unwrap_or(u32::MAX));
+
+    let mut points = Vec::new();
+
+    for pin in pins {
+        let response = client
+            .request(reqwest::Method::GET, endpoint(api_url, "XML_DM_REQUEST"))
+            .query(&prelude::LinesParams::new(pin.gid()))
+            .send()
+            .await;
+
+        // Lines are only a hint, so a stop is still listed without them if they can't be looked up
+        let lines = match response {
+            Ok(response) => response.json::<prelude::LinesResponse>().await,
+            Err(err) => Err(err),
+        };
+
+        let point = pin.point();
+        let lines = lines
+            .map(|res| res.lines().join(", "))
+            .unwrap_or_else(|err| {
+                log::warn!("Linien für {name} nicht abrufbar: {err}", name = point.name);
+                String::new()
+            });
+
+        let label = format!("{name} ({distance} m) {lines}", name = point.name, distance = pin.distance().unwrap_or_default());
+
+        points.push((point, label.trim_end().to_owned()));
+    }
+
+    Ok(points)
+}
+
+async fn save_point(point: &prelude::Point, api_url: &reqwest::Url) -> Result<()> {
     if !prelude::confirm(format!("Es wurde {point} gewählt. Speichern?\n (y/n) # ", point=point.name)).await {
         return Ok(());
     }
@@ -118,15 +191,34 @@ async fn save_point(point: &prelude::Point) -> Result<()> {
         config.departure.clear();
     }
 
-    config.departure.push(DepartureConfig {
-        point: point.ids.gid.clone(),
-    });
+    config.departure.push(departure(&config, point, api_url));
 
     tokio::fs::write(&path, toml::to_string_pretty(&config).map_err(Error::other)?).await?;
 
     Ok(())
 }
 
+/// Records which EFA instance the stop was found in, preferring a matching entry in `[providers]` over the plain URL.
+fn departure(config: &Config, point: &prelude::Point, api_url: &reqwest::Url) -> DepartureConfig {
+    let same = |url: &str| url.trim_end_matches('/') == api_url.as_str().trim_end_matches('/');
+
+    let provider = config.providers.iter()
+        .find(|(_, provider)| same(&provider.url))
+        .map(|(name, _)| name.clone());
+
+    let url = match provider {
+        None if !same(EFA_API) => Some(api_url.to_string()),
+        _ => None,
+    };
+
+    DepartureConfig {
+        point: point.ids.gid.clone(),
+        url,
+        provider,
+        ..Default::default()
+    }
+}
+
 async fn write_config(config: &Config, path: impl AsRef<Path>) -> Result<()> {
     tokio::fs::write(path, toml::to_string(config).map_err(Error::other)?).await
 }
\ No newline at end of file